@@ -0,0 +1,23 @@
+//! A tiny GTK window with a few buttons, used as the target application when testing
+//! the AT-SPI hint mode on a private accessibility bus (see `tests/hint.rs`).
+
+use gtk4::{
+    prelude::{ApplicationExt, ApplicationExtManual, BoxExt, GtkWindowExt},
+    Application, ApplicationWindow, Button, Orientation,
+};
+
+fn main() {
+    glib::set_application_name("gcn-hint-target");
+    let app = Application::new(Some("org.example.GcnHintTarget"), Default::default());
+    app.connect_activate(|app| {
+        let window = ApplicationWindow::new(app);
+        window.set_title(Some("gcn hint target"));
+        let buttons = gtk4::Box::new(Orientation::Vertical, 8);
+        for name in ["Alpha", "Beta", "Gamma"] {
+            buttons.append(&Button::with_label(name));
+        }
+        window.set_child(Some(&buttons));
+        window.present();
+    });
+    app.run();
+}
@@ -0,0 +1,17 @@
+#!/bin/sh
+# Compares the time to present the grid overlay with both renderers.
+# The server must be running; extra arguments are passed to the client.
+# Usage: scripts/bench-present.sh [runs] [client args...]
+
+runs=${1:-5}
+[ $# -gt 0 ] && shift
+client=${CLIENT:-gtk-cursor-navigator}
+
+for renderer in widgets canvas; do
+    echo "== $renderer =="
+    i=0
+    while [ "$i" -lt "$runs" ]; do
+        "$client" --renderer "$renderer" --benchmark "$@" | grep 'Time to present'
+        i=$((i + 1))
+    done
+done
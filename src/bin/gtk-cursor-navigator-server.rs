@@ -2,22 +2,279 @@
 #![allow(unused_variables)]
 
 use clap::{Arg, value_parser, Command};
-// use std::env;
-use std::net::{TcpListener, TcpStream};
-use std::io::Write;
+use std::env;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::fs;
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use log::{error, info, warn, LevelFilter};
 use gtk_cursor_navigator::{
-    conf::{APP_NAME, CONF_DIR_DEFAULT, CONF_FILE_SUFFIX, STYLE_FILE_SUFFIX,
-           LOG_DIR_DEFAULT, PathBufExt, expand_path,},
-    generate_token_list, SharedData, SelectedKeys,
+    conf::{self, APP_NAME, CONF_DIR_DEFAULT, CONF_FILE_SUFFIX, STYLE_FILE_SUFFIX,
+           LOG_DIR_DEFAULT, Conf, ConfLog, ProfileMatcher, expand_path,},
+    conf_doc,
+    daemon::{self, PidFile},
+    focus::FocusedWindow,
+    logging,
+    generate_grid_tokens, SharedData,
+    protocol::{read_request, send_request, Request, ServerStatus},
+    schema,
 };
 
-fn handle_client(mut stream: TcpStream, shared_data: &SharedData) {
-    let json = serde_json::to_string(shared_data)
-        .expect("Failed to serialize shared data");
+/// State kept by the server for as long as it runs, shared by all client threads.
+/// The shared data of every profile is prepared up front, so answering a client is
+/// only a lookup.
+struct ServerState {
+    shared_data: SharedData,
+    profiles: BTreeMap<String, SharedData>,
+    /// The compiled patterns of the profiles, in name order, matched against the windows
+    /// clients report.
+    matchers: Vec<(String, ProfileMatcher)>,
+    /// The profile set with a `set_profile` request, used for every client not asking
+    /// for one by name.
+    active_profile: Option<String>,
+    /// The file given with `--config`, replacing the system and user files.
+    explicit_config: Option<PathBuf>,
+    /// The files the configuration was last loaded from, lowest precedence first,
+    /// included files among them.
+    config_files: Vec<PathBuf>,
+    loaded_config_modified: Option<SystemTime>,
+    started: Instant,
+    clients_served: u64,
+    reloads: u64,
+    last_error: Option<String>,
+}
+
+impl ServerState {
+    fn new(config: Conf, explicit_config: Option<PathBuf>, config_files: Vec<PathBuf>) -> Self {
+        let mut state = ServerState {
+            shared_data: SharedData {
+                config: Conf::default(),
+                tokens: Vec::new(),
+                last_selection: None,
+                profile: None,
+            },
+            profiles: BTreeMap::new(),
+            matchers: Vec::new(),
+            active_profile: None,
+            loaded_config_modified: newest_modified_time(&config_files),
+            explicit_config,
+            config_files,
+            started: Instant::now(),
+            clients_served: 0,
+            reloads: 0,
+            last_error: None,
+        };
+        state.load(config);
+        state
+    }
+
+    /// Replaces the configuration and prepares the shared data of every profile.
+    /// Remembered selections are dropped, since the cells may have changed.
+    fn load(&mut self, config: Conf) {
+        let mut profiles = BTreeMap::new();
+        let mut matchers = Vec::new();
+        for (name, profile) in &config.profiles {
+            let matcher = match profile.matcher() {
+                Ok(matcher) => matcher,
+                Err(e) => {
+                    self.report_error(format!("Ignoring profile {:?} with an invalid pattern: {}", name, e));
+                    continue;
+                }
+            };
+            match config.with_profile(name) {
+                Ok(profile_config) => {
+                    matchers.push((name.clone(), matcher));
+                    profiles.insert(name.clone(), SharedData {
+                        tokens: generate_grid_tokens(&profile_config.grid),
+                        config: profile_config,
+                        last_selection: None,
+                        profile: Some(name.clone()),
+                    });
+                }
+                Err(e) => self.report_error(format!("Ignoring profile: {}", e)),
+            }
+        }
+        // Generate token list using the common function, honouring the label mode.
+        let tokens = generate_grid_tokens(&config.grid);
+        self.shared_data = SharedData {
+            config,
+            tokens,
+            last_selection: None,
+            profile: None,
+        };
+        self.profiles = profiles;
+        self.matchers = matchers;
+        if let Some(name) = &self.active_profile
+            && !self.profiles.contains_key(name)
+        {
+            let name = self.active_profile.take().unwrap();
+            self.report_error(format!("The active profile {:?} is gone, using the main configuration.", name));
+        }
+    }
+
+    /// Reads the configuration files again, picking up added or removed drop-ins, and
+    /// keeps the current configuration when that fails.
+    fn reload(&mut self) {
+        let files = conf::config_files(self.explicit_config.as_deref());
+        match conf::load_config(&files) {
+            Ok((config, files)) => {
+                let modified = newest_modified_time(&files);
+                self.load(config);
+                info!(files:? = files; "Reloaded the configuration");
+                self.config_files = files;
+                self.loaded_config_modified = modified;
+                self.reloads += 1;
+            }
+            Err(e) => self.report_error(e),
+        }
+    }
+
+    /// The name of the first profile, in name order, whose patterns match `window`.
+    fn matching_profile(&self, window: &FocusedWindow) -> Option<String> {
+        self.matchers
+            .iter()
+            .find(|(_, matcher)| matcher.matches(window))
+            .map(|(name, _)| name.clone())
+    }
+
+    fn report_error(&mut self, error: String) {
+        error!("{}", error);
+        self.last_error = Some(error);
+    }
+
+    fn status(&self) -> ServerStatus {
+        ServerStatus {
+            uptime: self.started.elapsed().as_secs(),
+            config_files: self.config_files.iter().map(|file| file.display().to_string()).collect(),
+            loaded_config_modified: self.loaded_config_modified,
+            config_modified: self.current_config_modified(),
+            token_count: self.shared_data.tokens.len(),
+            profiles: self.profiles.keys().cloned().collect(),
+            active_profile: self.active_profile.clone(),
+            clients_served: self.clients_served,
+            reloads: self.reloads,
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    /// The latest modification time among the configuration files as they are now:
+    /// files added since the last load, and the ones read then, included files too.
+    fn current_config_modified(&self) -> Option<SystemTime> {
+        let mut files = conf::config_files(self.explicit_config.as_deref());
+        files.extend(self.config_files.iter().cloned());
+        newest_modified_time(&files)
+    }
+
+    /// The shared data of the profile `name`, or of the main configuration for `None`
+    /// and unknown names.
+    fn shared_data_mut(&mut self, name: Option<&str>) -> &mut SharedData {
+        match name {
+            Some(name) if self.profiles.contains_key(name) => self.profiles.get_mut(name).unwrap(),
+            Some(name) => {
+                warn!("Unknown profile {:?}, using the main configuration.", name);
+                &mut self.shared_data
+            }
+            None => &mut self.shared_data,
+        }
+    }
+}
+
+/// Ends the accept loop of the server. The loop only looks at the flag when a connection
+/// comes in, so requesting the shutdown also connects to the listener.
+struct Shutdown {
+    requested: AtomicBool,
+    addr: SocketAddr,
+}
+
+impl Shutdown {
+    fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        let _ = TcpStream::connect(self.addr);
+    }
+
+    fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+/// The modification time of `path`, at the precision of the file system.
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// The latest modification time among `files`.
+fn newest_modified_time(files: &[PathBuf]) -> Option<SystemTime> {
+    files.iter().filter_map(|file| modified_time(file)).max()
+}
+
+fn handle_client(mut stream: TcpStream, state: &Mutex<ServerState>, shutdown: &Shutdown) {
+    let request = match read_request(&stream) {
+        Ok(request) => request,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return,
+        Err(e) => {
+            state.lock().unwrap().report_error(format!("Invalid request from client: {}", e));
+            return;
+        }
+    };
+    state.lock().unwrap().clients_served += 1;
+    let json = match request {
+        Request::SharedData { profile, window } => {
+            let mut state = state.lock().unwrap();
+            // Profiles match against the main configuration's patterns, which are the
+            // only ones kept. The active profile applies to every window.
+            let name = profile.or_else(|| state.active_profile.clone()).or_else(|| {
+                window.and_then(|window| state.matching_profile(&window))
+            });
+            serde_json::to_string(state.shared_data_mut(name.as_deref()))
+                .expect("Failed to serialize shared data")
+        }
+        Request::RecordSelection { index, profile } => {
+            let mut state = state.lock().unwrap();
+            let shared_data = state.shared_data_mut(profile.as_deref());
+            if index < shared_data.tokens.len() {
+                shared_data.last_selection = Some(index);
+            }
+            "null".to_string()
+        }
+        Request::Status => {
+            let state = state.lock().unwrap();
+            serde_json::to_string(&state.status()).expect("Failed to serialize status")
+        }
+        Request::Reload => {
+            let mut state = state.lock().unwrap();
+            state.reload();
+            serde_json::to_string(&state.status()).expect("Failed to serialize status")
+        }
+        Request::SetProfile { profile } => {
+            let mut state = state.lock().unwrap();
+            match profile {
+                Some(name) if !state.profiles.contains_key(&name) => {
+                    state.report_error(format!("Cannot activate unknown profile {:?}", name));
+                }
+                profile => {
+                    info!(profile:? = profile; "Activated a profile");
+                    state.active_profile = profile;
+                }
+            }
+            serde_json::to_string(&state.status()).expect("Failed to serialize status")
+        }
+        Request::Shutdown => {
+            let _ = stream.write_all(b"null");
+            info!("Shutdown requested by a client");
+            shutdown.request();
+            return;
+        }
+    };
     stream.write_all(json.as_bytes())
-        .expect("Failed to write shared data to stream");
+        .expect("Failed to write response to stream");
 }
 
 fn main() {
@@ -26,7 +283,7 @@ fn main() {
     // Set up static configuration paths.
     APP_NAME.set(name).expect("APP_NAME already initialized");
     CONF_DIR_DEFAULT
-        .set(PathBuf::from(format!("~/.config/{}", name)))
+        .set(conf::user_config_dir())
         .expect("CONF_DIR_DEFAULT already initialized");
     CONF_FILE_SUFFIX.set(".toml").expect("CONF_FILE_SUFFIX already initialized");
     STYLE_FILE_SUFFIX.set(".css").expect("STYLE_FILE_SUFFIX already initialized");
@@ -34,13 +291,6 @@ fn main() {
         .set(PathBuf::from("/tmp/"))
         .expect("LOG_DIR_DEFAULT already initialized");
 
-    // Build the default configuration file path.
-    let config_default_static: &'static PathBuf = {
-        let config_dir = PathBuf::from("~/.config").join(name);
-        let config_file_default = config_dir.join("config.toml");
-        Box::leak(Box::new(config_file_default))
-    };
-
     // Use Clap to parse command-line arguments for the server.
     let command = Command::new(name)
         .author("IcyTomato")
@@ -51,13 +301,8 @@ fn main() {
                 .short('c')
                 .long("config")
                 .value_name("CONFIG")
-                .help("Sets the server config file path")
-                .value_parser(value_parser!(PathBuf))
-                .default_value(
-                    config_default_static
-                        .to_str()
-                        .expect("The default config path must be valid Unicode!")
-                ),
+                .help("Reads this config file (and its conf.d) instead of the system and user ones")
+                .value_parser(value_parser!(PathBuf)),
         )
         .arg(
             Arg::new("port")
@@ -67,48 +312,336 @@ fn main() {
                 .help("Sets the port for the server to listen on")
                 .value_parser(value_parser!(u16))
                 .default_value("7878"),
+        )
+        .arg(
+            Arg::new("log_level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("Overrides the log level of the configuration")
+                .value_parser(["error", "warn", "info", "debug", "trace"]),
+        )
+        .arg(
+            Arg::new("replace")
+                .long("replace")
+                .help("Stops the server already running, if any, and takes its place")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .subcommand(Command::new("status").about("Prints the status of the running server"))
+        .subcommand(Command::new("shutdown").about("Stops the running server"))
+        .subcommand(
+            Command::new("set-profile")
+                .about("Makes the running server use a profile for every client, or none without a name")
+                .arg(Arg::new("name").value_name("NAME")),
+        )
+        .subcommand(
+            Command::new("reload").about("Makes the running server read its config file again"),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Inspects the configuration")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("dump")
+                        .about("Prints the merged config files")
+                        .arg(
+                            Arg::new("effective")
+                                .long("effective")
+                                .help("Prints every setting in effect, with defaults and GCN_ variables applied")
+                                .action(clap::ArgAction::SetTrue),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("init-config")
+                .about("Writes the default configuration, with every setting documented")
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .value_name("FILE")
+                        .help("Writes to this file instead of the user's config.toml")
+                        .value_parser(value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Overwrites the file if it exists")
+                        .action(clap::ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("schema").about("Prints the JSON Schema of config files, for editors"),
+        )
+        .subcommand(
+            Command::new("install-service")
+                .about("Writes systemd user units starting the server on the first connection"),
         );
     let matches = command.get_matches();
 
-    let config_file = matches.get_one::<PathBuf>("config")
-        .expect("The `config` option not found");
-
-    let expanded_config = expand_path(config_file.to_str().unwrap());
-    println!("Server using config file: {:?}", expanded_config);
-
-    let config = expanded_config.read_config();
-    println!("Server configuration:\n{:#?}", config);
-
-    // Generate token list using the common function.
-    let total_cells = (config.grid.rows as usize) * (config.grid.columns as usize);
-    // let tokens = generate_token_list(total_cells, &SelectedKeys::default());
-    let selected_keys =
-        SelectedKeys::new(&config.grid.key_left, &config.grid.key_right);
-    let tokens =
-        generate_token_list(total_cells, &selected_keys);
-    let shared_data = SharedData {
-        config,
-        tokens,
-    };
+    let log_level = matches.get_one::<String>("log_level").and_then(|level| level.parse::<LevelFilter>().ok());
+    logging::init(name, &ConfLog { file: false, ..ConfLog::default() }, log_level);
 
     let port = *matches.get_one::<u16>("port").unwrap();
     let addr = format!("127.0.0.1:{}", port);
-    let listener = TcpListener::bind(&addr)
-        .expect("Failed to bind TCP listener");
-    println!("Server listening on {}", addr);
+    match matches.subcommand_name() {
+        Some("status") => query_status(&addr, &Request::Status),
+        Some("reload") => query_status(&addr, &Request::Reload),
+        Some("set-profile") => {
+            let profile = matches
+                .subcommand_matches("set-profile")
+                .and_then(|set_matches| set_matches.get_one::<String>("name").cloned());
+            query_status(&addr, &Request::SetProfile { profile })
+        }
+        Some("shutdown") => {
+            if let Err(e) = send_request(&addr, &Request::Shutdown) {
+                eprintln!("No server answering on {}: {}", addr, e);
+                process::exit(1);
+            }
+            process::exit(0);
+        }
+        _ => {}
+    }
+
+    let explicit_config = matches.get_one::<PathBuf>("config")
+        .map(|file| expand_path(file.to_str().unwrap()));
+    let config_files = conf::config_files(explicit_config.as_deref());
+
+    match matches.subcommand() {
+        Some(("init-config", init_matches)) => {
+            let path = init_matches
+                .get_one::<PathBuf>("path")
+                .map(|path| expand_path(path.to_str().unwrap()))
+                .unwrap_or_else(|| conf::user_config_dir().join("config.toml"));
+            init_config(&path, init_matches.get_flag("force"));
+        }
+        Some(("schema", _)) => {
+            println!("{}", serde_json::to_string_pretty(&schema::conf_schema()).unwrap());
+            process::exit(0);
+        }
+        Some(("install-service", _)) => install_service(name, &addr, explicit_config.as_deref()),
+        Some(("config", config_matches)) => {
+            if let Some(("dump", dump_matches)) = config_matches.subcommand() {
+                dump_config(&config_files, dump_matches.get_flag("effective"));
+            }
+        }
+        _ => {}
+    }
+
+    // Only one server runs at a time; it holds the pid file until it stops.
+    let pid_path = PidFile::default_path(name);
+    let pid_file = if matches.get_flag("replace") {
+        PidFile::take_over(&pid_path, Duration::from_secs(5))
+    } else {
+        PidFile::lock(&pid_path)
+    };
+    let pid_file = match pid_file {
+        Ok(pid_file) => pid_file,
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+            let pid = daemon::running_pid(&pid_path).map_or("unknown".to_string(), |pid| pid.to_string());
+            error!(
+                "Another server is already running (pid {}, see {:?}). Use --replace to take over.",
+                pid, pid_path
+            );
+            process::exit(1);
+        }
+        Err(e) => {
+            error!("Failed to lock the pid file {:?}: {}", pid_path, e);
+            process::exit(1);
+        }
+    };
+
+    let legacy_config = conf::legacy_config_file();
+    let user_config = conf::user_config_dir().join("config.toml");
+    if config_files.contains(&legacy_config) {
+        warn!("Reading the legacy {:?}; move it to {:?}.", legacy_config, user_config);
+    } else if explicit_config.is_none() && legacy_config.is_file() {
+        warn!("{:?} is not read since {:?} exists.", legacy_config, user_config);
+    }
+    info!(files:? = config_files; "Using the configuration files");
+    let (config, config_files, load_error) = match conf::load_config(&config_files) {
+        Ok((config, files_read)) => (config, files_read, None),
+        Err(e) => (Conf::default(), config_files, Some(format!("{}. Using the default configuration.", e))),
+    };
+    logging::init(name, &config.log, log_level);
+    let mut state = ServerState::new(config, explicit_config, config_files);
+    if let Some(e) = load_error {
+        state.report_error(e);
+    }
+    info!(
+        tokens = state.shared_data.tokens.len(),
+        profiles = state.profiles.len();
+        "Prepared the tokens"
+    );
+    let state = Arc::new(Mutex::new(state));
 
-    // Loop forever, handling clients by spawning a new thread.
+    // Under socket activation the listening socket comes from systemd, and `--port` only
+    // matters to the subcommands.
+    let listener = match daemon::activation_listener() {
+        Some(listener) => listener,
+        None => TcpListener::bind(&addr).unwrap_or_else(|e| {
+            error!("Failed to listen on {}: {}", addr, e);
+            process::exit(1);
+        }),
+    };
+    let local_addr = listener.local_addr().expect("The listener has no address");
+    info!(addr:% = local_addr; "Listening");
+    if let Err(e) = daemon::notify("READY=1") {
+        warn!("Failed to notify the service manager: {}", e);
+    }
+
+    let shutdown = Arc::new(Shutdown {
+        requested: AtomicBool::new(false),
+        addr: local_addr,
+    });
+    let mut signals = Signals::new([SIGTERM, SIGINT]).expect("Failed to install signal handlers");
+    let signal_shutdown = Arc::clone(&shutdown);
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            info!(signal = signal; "Shutting down on a signal");
+            signal_shutdown.request();
+        }
+    });
+
+    // Handle clients by spawning a new thread, until a shutdown is requested.
     for stream in listener.incoming() {
+        if shutdown.is_requested() {
+            break;
+        }
         match stream {
             Ok(stream) => {
-                let sd = shared_data.clone();
+                let state = Arc::clone(&state);
+                let shutdown = Arc::clone(&shutdown);
                 thread::spawn(move || {
-                    handle_client(stream, &sd);
+                    handle_client(stream, &state, &shutdown);
                 });
             }
             Err(e) => {
-                eprintln!("Error accepting client connection: {:?}", e);
+                warn!("Error accepting client connection: {:?}", e);
+            }
+        }
+    }
+    let _ = daemon::notify("STOPPING=1");
+    // The port is freed before the lock, so a server taking over can bind it right away.
+    drop(listener);
+    drop(pid_file);
+    info!("Server stopped");
+}
+
+/// Prints the configuration from `files`, either as merged from them or, when
+/// `effective`, every setting in effect, then exits.
+fn dump_config(files: &[PathBuf], effective: bool) -> ! {
+    let loaded = if effective {
+        conf::load_config(files).and_then(|(config, read)| {
+            toml::Value::try_from(config).map(|value| (value, read)).map_err(|e| e.to_string())
+        })
+    } else {
+        conf::read_config_files(files)
+    };
+    let text = loaded.and_then(|(value, read)| {
+        toml::to_string(&value).map(|text| (text, read)).map_err(|e| e.to_string())
+    });
+    match text {
+        Ok((text, read)) => {
+            for file in read {
+                println!("# From {}", file.display());
             }
+            print!("{}", text);
+            process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Writes the default configuration, documented, to `path`, then exits. An existing file
+/// is only replaced with `force`.
+fn init_config(path: &Path, force: bool) -> ! {
+    if path.exists() && !force {
+        eprintln!("{:?} already exists; use --force to overwrite it.", path);
+        process::exit(1);
+    }
+    if let Some(dir) = path.parent()
+        && let Err(e) = fs::create_dir_all(dir)
+    {
+        eprintln!("Failed to create {:?}: {}", dir, e);
+        process::exit(1);
+    }
+    if let Err(e) = fs::write(path, conf_doc::commented_default()) {
+        eprintln!("Failed to write {:?}: {}", path, e);
+        process::exit(1);
+    }
+    println!("Wrote {:?}", path);
+    process::exit(0);
+}
+
+/// Writes a socket unit listening on `addr` and a service unit running this executable,
+/// with `config` when given, to the systemd user unit directory, then exits.
+fn install_service(name: &str, addr: &str, config: Option<&Path>) -> ! {
+    let unit_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(|| expand_path("~/.config"), PathBuf::from)
+        .join("systemd/user");
+    let exe = env::current_exe().unwrap_or_else(|e| {
+        eprintln!("Failed to find the server executable: {}", e);
+        process::exit(1);
+    });
+    let args: Vec<String> = match config {
+        Some(config) => vec!["--config".to_string(), config.display().to_string()],
+        None => Vec::new(),
+    };
+    let units = [
+        (format!("{}.socket", name), daemon::socket_unit(name, addr)),
+        (format!("{}.service", name), daemon::service_unit(name, &exe, &args)),
+    ];
+    if let Err(e) = fs::create_dir_all(&unit_dir) {
+        eprintln!("Failed to create {:?}: {}", unit_dir, e);
+        process::exit(1);
+    }
+    for (file_name, contents) in &units {
+        let path = unit_dir.join(file_name);
+        if let Err(e) = fs::write(&path, contents) {
+            eprintln!("Failed to write {:?}: {}", path, e);
+            process::exit(1);
         }
+        println!("Wrote {:?}", path);
+    }
+    println!("Enable them with:");
+    println!("    systemctl --user daemon-reload");
+    println!("    systemctl --user enable --now {}.socket", name);
+    process::exit(0);
+}
+
+/// Sends `request` to the server at `addr`, prints the status it answers with and exits,
+/// with an error when a profile to activate was refused.
+fn query_status(addr: &str, request: &Request) -> ! {
+    let answer = send_request(addr, request).unwrap_or_else(|e| {
+        eprintln!("No server answering on {}: {}", addr, e);
+        process::exit(1);
+    });
+    let status: ServerStatus = serde_json::from_str(&answer).unwrap_or_else(|e| {
+        eprintln!("Invalid status from the server: {}", e);
+        process::exit(1);
+    });
+    // Seconds since the Unix epoch, with the sub-second part a change may differ by.
+    let time = |modified: Option<SystemTime>| {
+        modified
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or("unknown".to_string(), |since| format!("{}.{:09}", since.as_secs(), since.subsec_nanos()))
+    };
+    println!("Uptime:          {} s", status.uptime);
+    println!("Config files:    {}", status.config_files.join(", "));
+    println!("Loaded mtime:    {}", time(status.loaded_config_modified));
+    println!("Current mtime:   {}", time(status.config_modified));
+    if status.config_is_stale() {
+        println!("                 changed since it was loaded, run `reload` to apply it");
     }
+    println!("Tokens:          {}", status.token_count);
+    println!("Profiles:        {}", status.profiles.join(", "));
+    println!("Active profile:  {}", status.active_profile.as_deref().unwrap_or("none"));
+    println!("Clients served:  {}", status.clients_served);
+    println!("Reloads:         {}", status.reloads);
+    println!("Last error:      {}", status.last_error.as_deref().unwrap_or("none"));
+    let refused = matches!(request, Request::SetProfile { profile } if *profile != status.active_profile);
+    process::exit(if refused { 1 } else { 0 });
 }
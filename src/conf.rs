@@ -1,9 +1,13 @@
+use schemars::JsonSchema;
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 use std::default::Default;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
+use crate::focus::FocusedWindow;
+use crate::pointer::PointerButton;
 
 pub static APP_NAME: OnceLock<&str> = OnceLock::new();
 pub static CONF_DIR_DEFAULT: OnceLock<PathBuf> = OnceLock::new();
@@ -11,25 +15,136 @@ pub static CONF_FILE_SUFFIX: OnceLock<&str> = OnceLock::new();
 pub static STYLE_FILE_SUFFIX: OnceLock<&str> = OnceLock::new();
 pub static LOG_DIR_DEFAULT: OnceLock<PathBuf> = OnceLock::new();
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+/// The configuration shared by the server and the client.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Default, Clone)]
 #[serde(default)]
 pub struct Conf {
+    /// Layout of the grid and the keys its labels are made of.
     pub grid: ConfGrid,
+    /// Colors, lines and fonts of the overlay. Colors are CSS colors.
     pub theme: ConfTheme,
+    /// Margins of the output left uncovered by the overlay, in pixels, e.g. for a bar.
     pub reserved: ReservedNotCovered,
+    /// Keyboard shortcuts of the overlay.
     pub shortcut: ConfShortcut,
+    /// Settings of the hint mode, which labels UI elements found through AT-SPI.
+    pub hint: ConfHint,
+    /// Settings of the nudge mode, which keeps the overlay alive after a selection so the
+    /// pointer can be adjusted with directional keys.
+    pub nudge: ConfNudge,
+    /// Settings of the scroll actions, which keep the overlay alive after a selection so
+    /// the area under the pointer can be scrolled with keys. Held keys repeat with the
+    /// keyboard's auto-repeat.
+    pub scroll: ConfScroll,
+    /// Logging settings, used by the server and, through it, the client.
+    pub log: ConfLog,
+    /// Profiles by name. Besides its patterns, a profile holds sections (`grid`, `theme`,
+    /// `reserved`, ...) whose settings replace the main ones, including the keys and label
+    /// mode the tokens are made of.
+    pub profiles: BTreeMap<String, ConfProfile>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl Conf {
+    /// The configuration used under profile `name`: the profile's sections are merged
+    /// into the ones of this configuration, and profiles are dropped.
+    pub fn with_profile(&self, name: &str) -> Result<Conf, String> {
+        let profile = self.profiles.get(name).ok_or(format!("No profile named {:?}", name))?;
+        let mut value = toml::Value::try_from(self).map_err(|e| e.to_string())?;
+        value.as_table_mut().expect("Conf serializes to a table").remove("profiles");
+        let mut overrides = profile.sections.clone();
+        overrides.remove("profiles");
+        merge_values(&mut value, toml::Value::Table(overrides));
+        value.try_into().map_err(|e| format!("Profile {:?}: {}", name, e))
+    }
+
+    /// Whether any profile has patterns, so the focused window can change the
+    /// configuration.
+    pub fn has_window_patterns(&self) -> bool {
+        self.profiles.values().any(|profile| profile.app_id.is_some() || profile.title.is_some())
+    }
+}
+
+/// Overrides applied while a matching window is focused, or when selected by name. Every
+/// pattern given must match; a profile without patterns is only used when selected by name.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+#[serde(default)]
+pub struct ConfProfile {
+    /// Regular expression matched against the focused window's app id, or X11 class.
+    pub app_id: Option<String>,
+    /// Regular expression matched against the focused window's title.
+    pub title: Option<String>,
+    /// The sections whose settings replace the main ones.
+    #[serde(flatten)]
+    #[schemars(with = "BTreeMap<String, serde_json::Value>")]
+    pub sections: toml::value::Table,
+}
+
+impl ConfProfile {
+    /// Compiles the patterns, once per loaded configuration rather than per window.
+    pub fn matcher(&self) -> Result<ProfileMatcher, regex::Error> {
+        let compile = |pattern: &Option<String>| pattern.as_deref().map(regex::Regex::new).transpose();
+        Ok(ProfileMatcher { app_id: compile(&self.app_id)?, title: compile(&self.title)? })
+    }
+}
+
+/// The compiled patterns of a profile.
+#[derive(Debug, Clone)]
+pub struct ProfileMatcher {
+    app_id: Option<regex::Regex>,
+    title: Option<regex::Regex>,
+}
+
+impl ProfileMatcher {
+    /// Whether `window` matches every pattern. A profile without patterns matches no
+    /// window, it is only used when selected by name.
+    pub fn matches(&self, window: &FocusedWindow) -> bool {
+        let is_match = |pattern: &Option<regex::Regex>, text: &str| {
+            pattern.as_ref().is_none_or(|pattern| pattern.is_match(text))
+        };
+        (self.app_id.is_some() || self.title.is_some())
+            && is_match(&self.app_id, &window.app_id)
+            && is_match(&self.title, &window.title)
+    }
+}
+
+/// Layout of the grid and the keys its labels are made of.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(default)]
 pub struct ConfGrid {
+    /// Number of rows of the grid.
+    #[schemars(range(min = 1))]
     pub rows: u8,
+    /// Number of columns of the grid.
+    #[schemars(range(min = 1))]
     pub columns: u8,
     // pub width: u32,
     // pub height: u32,
-    // pub cover_screen: bool,
+    /// Spread the grid over the whole output; when off, the grid covers only the
+    /// focused window.
+    pub cover_screen: bool,
+    /// Keys of the left hand, separated by spaces, giving the first letter of the labels.
     pub key_left: String,
+    /// Keys of the right hand, separated by spaces, giving the second letter of the labels.
     pub key_right: String,
+    /// How the two letters of each cell label are chosen: "tokens" assigns an opaque
+    /// sequence of ergonomic combinations row-major, "coordinates" takes the row letter from
+    /// `key_left` and the column letter from `key_right`, like a spreadsheet.
+    pub label_mode: LabelMode,
+    /// Show the row and column letters along the edges in coordinates label mode. Only
+    /// grids of equal cells get headers: with `row_sizes`, `column_sizes` or `regions`
+    /// they are left out.
+    pub headers: bool,
+    /// How the grid view is rendered: "widgets" uses one GTK widget per cell, which a user
+    /// stylesheet can style; "canvas" draws everything in one pass, which presents much
+    /// faster on large grids but only follows the theme settings.
+    pub renderer: Renderer,
+    /// Heights of the rows, top to bottom; when set, they also give the number of rows.
+    pub row_sizes: Vec<Length>,
+    /// Widths of the columns, left to right; when set, they also give the number of columns.
+    pub column_sizes: Vec<Length>,
+    /// Rectangular parts of the overlay, each with a grid of its own. When set, they replace
+    /// the grid described above, and tokens are assigned across them in order.
+    pub regions: Vec<ConfRegion>,
 }
 
 impl Default for ConfGrid {
@@ -39,25 +154,194 @@ impl Default for ConfGrid {
             columns: 20,
             // width: 1920,
             // height: 1080,
-            // cover_screen: true,
+            cover_screen: true,
             key_left: "` 1 2 3 4 5 Q W E R T A S D F G Z X C V B".to_string(),
             key_right: "6 7 8 9 0 - = Y U I O P [ ] H J K L ; ' N M , . /".to_string(),
+            label_mode: LabelMode::Tokens,
+            headers: false,
+            renderer: Renderer::Widgets,
+            row_sizes: Vec::new(),
+            column_sizes: Vec::new(),
+            regions: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl ConfGrid {
+    /// The regions making up the grid view. Without configured regions, the grid is a
+    /// single region covering the whole overlay.
+    pub fn effective_regions(&self) -> Vec<ConfRegion> {
+        if !self.regions.is_empty() {
+            return self.regions.clone();
+        }
+        vec![ConfRegion {
+            name: String::new(),
+            rows: self.rows,
+            columns: self.columns,
+            row_sizes: self.row_sizes.clone(),
+            column_sizes: self.column_sizes.clone(),
+            ..ConfRegion::default()
+        }]
+    }
+
+    /// Whether all cells have the same size, up to a pixel.
+    pub fn is_uniform(&self) -> bool {
+        self.regions.is_empty() && self.row_sizes.is_empty() && self.column_sizes.is_empty()
+    }
+
+    /// The number of cells over all regions.
+    pub fn cell_count(&self) -> usize {
+        self.effective_regions()
+            .iter()
+            .map(|region| region.row_count() * region.column_count())
+            .sum()
+    }
+}
+
+/// A length in the grid configuration: an integer is a number of pixels, a decimal is a
+/// fraction. Fractional row heights and column widths share the space left by the pixel
+/// sizes in proportion to each other, so `[40, 1.0, 1.0]` is a 40 pixel band followed by
+/// two equal tracks. Fractional region bounds are fractions of the overlay size.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+pub enum Length {
+    Pixels(u32),
+    Fraction(f64),
+}
+
+/// A named rectangle of the overlay with its own rows and columns, e.g. a dense band over
+/// a toolbar above a coarse main area.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq)]
+#[serde(default)]
+pub struct ConfRegion {
+    /// Name of the region, for the logs.
+    pub name: String,
+    /// Left edge of the region.
+    pub x: Length,
+    /// Top edge of the region.
+    pub y: Length,
+    /// Width of the region.
+    pub width: Length,
+    /// Height of the region.
+    pub height: Length,
+    /// Number of rows of the region.
+    pub rows: u8,
+    /// Number of columns of the region.
+    pub columns: u8,
+    /// Heights of the rows, top to bottom; when set, they also give the number of rows.
+    pub row_sizes: Vec<Length>,
+    /// Widths of the columns, left to right; when set, they also give the number of columns.
+    pub column_sizes: Vec<Length>,
+}
+
+impl Default for ConfRegion {
+    fn default() -> Self {
+        ConfRegion {
+            name: String::new(),
+            x: Length::Pixels(0),
+            y: Length::Pixels(0),
+            width: Length::Fraction(1.0),
+            height: Length::Fraction(1.0),
+            rows: 1,
+            columns: 1,
+            row_sizes: Vec::new(),
+            column_sizes: Vec::new(),
+        }
+    }
+}
+
+impl ConfRegion {
+    pub fn row_count(&self) -> usize {
+        if self.row_sizes.is_empty() { self.rows as usize } else { self.row_sizes.len() }
+    }
+
+    pub fn column_count(&self) -> usize {
+        if self.column_sizes.is_empty() { self.columns as usize } else { self.column_sizes.len() }
+    }
+}
+
+/// How the two letters of each cell label are chosen.
+/// - `Tokens`: an opaque sequence of ergonomic combinations, assigned row-major.
+/// - `Coordinates`: the first letter selects the row (from `key_left`) and the
+///   second selects the column (from `key_right`), like a spreadsheet.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelMode {
+    #[default]
+    Tokens,
+    Coordinates,
+}
+
+/// How the grid view is rendered.
+/// - `Widgets`: one GTK widget per cell, which a user stylesheet can style.
+/// - `Canvas`: all cells, labels and lines drawn in a single pass on one drawing area,
+///   which presents much faster on large grids but only follows the theme settings.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Renderer {
+    #[default]
+    Widgets,
+    Canvas,
+}
+
+/// Colors, lines and fonts of the overlay. Colors are CSS colors.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(default)]
 pub struct ConfTheme {
+    /// Color of the cell backgrounds.
     pub background_color: String,
+    /// Color of the label text.
     pub foreground_color: String,
+    /// Width of the grid lines in pixels.
     pub line_pixel: u8,
+    /// Color of the grid lines.
     pub line_color: String,
+    /// How the grid lines are drawn.
+    pub line_style: LineStyle,
+    /// Length of the dashes and gaps of dashed lines.
+    pub line_dash: u8,
+    /// Number of guide divisions drawn inside each cell, 0 or 1 for none.
+    pub subgrid: u8,
+    /// Color of the guide lines inside the cells.
+    pub subgrid_color: String,
+    /// Opacity of the guide lines inside the cells.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub subgrid_opacity: f32,
+    /// Opacity of the whole overlay window, applied on top of the opacities below.
+    #[schemars(range(min = 0.0, max = 1.0))]
     pub opacity: f32,
-    // pub background_opacity: f32,
-    // pub foreground_opacity: f32,
+    /// Opacity of the cell backgrounds, so the content underneath stays visible.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub background_opacity: f32,
+    /// Opacity of the label text.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub foreground_opacity: f32,
+    /// Opacity of the grid lines.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub line_opacity: f32,
+    /// Draw no cell backgrounds at all, only the labels on small backing pills.
+    pub transparent_cells: bool,
+    /// CSS font weight of the labels, by name or number, e.g. "Bold" or "600".
+    #[schemars(schema_with = "crate::schema::font_weight")]
     pub font_weight: String,
+    /// Font size of the labels in pixels with the "fixed" font sizing.
     pub font_size: u8,
+    /// How the label font size is chosen: "fixed" always uses `font_size`, "fit" the
+    /// largest size letting the label fit into its cell, "fraction" a fraction of the cell
+    /// height. No sizing lets a label outgrow its cell.
+    pub font_sizing: FontSizing,
+    /// Smallest font size chosen by the "fit" and "fraction" font sizings.
+    pub font_size_min: u8,
+    /// Largest font size chosen by the "fit" and "fraction" font sizings.
+    pub font_size_max: u8,
+    /// Fraction of the cell height used as font size with the "fraction" font sizing.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub font_size_fraction: f32,
+    /// Color of the typed part of the matching tokens, drawn with `foreground_opacity`.
+    pub highlight_color: String,
+    /// Opacity of the cells whose token doesn't match the typed input.
+    #[schemars(range(min = 0.0, max = 1.0))]
+    pub dimmed_opacity: f32,
 }
 
 impl Default for ConfTheme {
@@ -67,22 +351,65 @@ impl Default for ConfTheme {
             foreground_color: "#abb2bf".to_string(),
             line_pixel: 1,
             line_color: "#56b6c2".to_string(),
-            opacity: 0.8,
-            // background_opacity: 1.0,
-            // foreground_opacity: 1.0,
+            line_style: LineStyle::Solid,
+            line_dash: 6,
+            subgrid: 0,
+            subgrid_color: "#56b6c2".to_string(),
+            subgrid_opacity: 0.3,
+            opacity: 1.0,
+            background_opacity: 0.8,
+            foreground_opacity: 1.0,
+            line_opacity: 0.8,
+            transparent_cells: false,
             font_weight: "Bold".to_string(),
             font_size: 16,
+            font_sizing: FontSizing::Fixed,
+            font_size_min: 8,
+            font_size_max: 48,
+            font_size_fraction: 0.5,
+            highlight_color: "#e5c07b".to_string(),
+            dimmed_opacity: 0.35,
         }
     }
 }
 
+/// How the grid lines are drawn.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+/// How the label font size is chosen from the cell size.
+/// - `Fixed`: always `font_size`.
+/// - `Fit`: the largest size letting the label fit into its cell.
+/// - `Fraction`: a fraction of the cell height.
+///
+/// `Fit` and `Fraction` stay within `font_size_min` and `font_size_max`. No mode lets a label
+/// outgrow its cell, so the grid never exceeds the output.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FontSizing {
+    #[default]
+    Fixed,
+    Fit,
+    Fraction,
+}
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Margins of the output left uncovered by the overlay, in pixels, e.g. for a bar.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(default)]
 pub struct ReservedNotCovered {
+    /// Margin along the top edge.
     pub top: u32,
+    /// Margin along the bottom edge.
     pub bottom: u32,
+    /// Margin along the left edge.
     pub left: u32,
+    /// Margin along the right edge.
     pub right: u32,
 }
 
@@ -98,48 +425,176 @@ impl Default for ReservedNotCovered {
 }
 
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Keyboard shortcuts of the overlay. Keys are GDK key names (e.g. "h", "Left").
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
 #[serde(default)]
 pub struct ConfShortcut {
+    /// Numeric GDK key value closing the overlay without a selection (Escape).
     pub exit_key: u32,
+    /// Keys ending the nudge and scroll modes, leaving the pointer where it is.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub finish: Vec<String>,
+    /// Keys selecting the cell selected the previous time.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub last_cell: Vec<String>,
 }
 
 impl Default for ConfShortcut {
     fn default() -> Self {
         ConfShortcut {
             exit_key: 0xff1b, // Escape key numeric value.
+            finish: vec!["Return".to_string(), "KP_Enter".to_string(), "Escape".to_string()],
+            last_cell: vec!["Tab".to_string()],
+        }
+    }
+}
+
+/// Settings of the hint mode, which labels UI elements found through AT-SPI.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Default)]
+#[serde(default)]
+pub struct ConfHint {
+    /// Also click the element after moving the pointer to its center.
+    pub click: bool,
+    /// The button used for the click.
+    pub click_button: PointerButton,
+}
+
+/// Settings of the nudge mode, which keeps the overlay alive after a selection so the
+/// pointer can be adjusted with directional keys. Keys are GDK key names (e.g. "h", "Left").
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(default)]
+pub struct ConfNudge {
+    /// Turns the nudge mode on.
+    pub enabled: bool,
+    /// Pixels moved per key press.
+    pub step: u32,
+    /// Pixels moved per key press while `large_modifier` is held.
+    pub large_step: u32,
+    /// The modifier selecting `large_step`: "Shift", "Control", "Alt" or "Super", in any case.
+    #[schemars(schema_with = "crate::schema::modifier")]
+    pub large_modifier: String,
+    /// Keys moving the pointer left.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub left: Vec<String>,
+    /// Keys moving the pointer down.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub down: Vec<String>,
+    /// Keys moving the pointer up.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub up: Vec<String>,
+    /// Keys moving the pointer right.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub right: Vec<String>,
+}
+
+impl Default for ConfNudge {
+    fn default() -> Self {
+        ConfNudge {
+            enabled: false,
+            step: 5,
+            large_step: 50,
+            large_modifier: "Shift".to_string(),
+            left: vec!["h".to_string(), "Left".to_string()],
+            down: vec!["j".to_string(), "Down".to_string()],
+            up: vec!["k".to_string(), "Up".to_string()],
+            right: vec!["l".to_string(), "Right".to_string()],
         }
     }
 }
 
-/// Trait to let a PathBuf read and parse a configuration file.
+/// Settings of the scroll actions, which keep the overlay alive after a selection so the
+/// area under the pointer can be scrolled with keys. Held keys repeat with the keyboard's
+/// auto-repeat. Keys are GDK key names.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(default)]
+pub struct ConfScroll {
+    /// Turns the scroll actions on.
+    pub enabled: bool,
+    /// Wheel detents per key press.
+    pub step: i32,
+    /// Scroll by `high_resolution_step` instead of whole detents.
+    pub high_resolution: bool,
+    /// Scroll amount per key press in 1/120 detent units, with `high_resolution`.
+    pub high_resolution_step: i32,
+    /// Keys scrolling up.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub up: Vec<String>,
+    /// Keys scrolling down.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub down: Vec<String>,
+    /// Keys scrolling left.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub left: Vec<String>,
+    /// Keys scrolling right.
+    #[schemars(schema_with = "crate::schema::key_names")]
+    pub right: Vec<String>,
+}
+
+impl Default for ConfScroll {
+    fn default() -> Self {
+        ConfScroll {
+            enabled: false,
+            step: 1,
+            high_resolution: false,
+            high_resolution_step: 40,
+            up: vec!["u".to_string(), "Page_Up".to_string()],
+            down: vec!["d".to_string(), "Page_Down".to_string()],
+            left: vec!["comma".to_string()],
+            right: vec!["period".to_string()],
+        }
+    }
+}
+
+/// Logging settings, used by the server and, through it, the client.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(default)]
+pub struct ConfLog {
+    /// "error", "warn", "info", "debug" or "trace"; `--log-level` overrides it.
+    pub level: String,
+    /// Write one JSON object per record instead of a line of text.
+    pub json: bool,
+    /// Also log to `<dir>/<app name>.log`.
+    pub file: bool,
+    /// Directory of the log file; empty for the default log directory.
+    pub dir: String,
+    /// Size in bytes past which the log file is rotated.
+    pub max_size: u64,
+    /// Number of rotated log files kept.
+    pub max_files: u32,
+}
+
+impl Default for ConfLog {
+    fn default() -> Self {
+        ConfLog {
+            level: "info".to_string(),
+            json: false,
+            file: true,
+            dir: String::new(),
+            max_size: 1024 * 1024,
+            max_files: 3,
+        }
+    }
+}
+
+/// Trait to let a PathBuf read and parse a configuration file, the way the server loads
+/// its files: with includes and `GCN_` overrides.
 pub trait PathBufExt {
     fn read_config(&self) -> Conf;
+    fn try_read_config(&self) -> Result<Conf, String>;
 }
 
 impl PathBufExt for PathBuf {
+    /// Like `read_config`, but reports a missing or invalid file instead of falling back
+    /// to the default configuration.
+    fn try_read_config(&self) -> Result<Conf, String> {
+        load_config(std::slice::from_ref(self)).map(|(conf, _)| conf)
+    }
+
     fn read_config(&self) -> Conf {
-        match fs::read_to_string(self) {
-            Ok(config_str) => {
-                toml::from_str(&config_str).unwrap_or_else(|err| {
-                    eprintln!(
-                        "Failed to parse configuration file: {}. Using default configuration.",
-                        err
-                    );
-                    Conf::default()
-                })
-            }
-            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {
-                eprintln!(
-                    "Configuration file {:?} not found. Using default configuration.",
-                    self
-                );
-                Conf::default()
-            }
-            Err(e) => {
-                panic!("#!Failed to read configuration file: {:?}", e);
-            }
-        }
+        self.try_read_config().unwrap_or_else(|e| {
+            log::warn!("{}. Using the default configuration.", e);
+            Conf::default()
+        })
     }
 }
 
@@ -158,3 +613,204 @@ pub fn expand_path(path_str: &str) -> PathBuf {
         PathBuf::from(path_str)
     }
 }
+
+/// The directory, under the XDG configuration directories, holding the configuration of
+/// both the server and the client.
+pub const CONFIG_DIR_NAME: &str = "gtk-cursor-navigator";
+
+/// The directory the server alone read its configuration from, before the client and the
+/// server shared one.
+pub const LEGACY_CONFIG_DIR_NAME: &str = "gtk-cursor-navigator-server";
+
+/// Prefix of the environment variables overriding settings, as in `GCN_GRID_ROWS=8`.
+pub const ENV_PREFIX: &str = "GCN_";
+
+/// `$XDG_CONFIG_HOME/gtk-cursor-navigator`, or `~/.config/gtk-cursor-navigator` without it.
+pub fn user_config_dir() -> PathBuf {
+    user_config_base().join(CONFIG_DIR_NAME)
+}
+
+/// The `config.toml` of the legacy server directory, next to `user_config_dir`.
+pub fn legacy_config_file() -> PathBuf {
+    user_config_base().join(LEGACY_CONFIG_DIR_NAME).join("config.toml")
+}
+
+fn user_config_base() -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| expand_path("~/.config"))
+}
+
+/// The system configuration directories from `$XDG_CONFIG_DIRS`, or `/etc/xdg` without
+/// it, most important first.
+pub fn system_config_dirs() -> Vec<PathBuf> {
+    let dirs = env::var("XDG_CONFIG_DIRS").unwrap_or_default();
+    let mut dirs: Vec<PathBuf> = dirs
+        .split(':')
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .collect();
+    if dirs.is_empty() {
+        dirs.push(PathBuf::from("/etc/xdg"));
+    }
+    dirs.into_iter().map(|dir| dir.join(CONFIG_DIR_NAME)).collect()
+}
+
+/// The configuration files to merge, lowest precedence first: the `config.toml` of each
+/// system directory, the user's `config.toml`, then the `*.toml` drop-ins of the user's
+/// `conf.d` directory in name order. Without a user `config.toml`, the legacy server one is
+/// read in its place. An `explicit` file replaces the system and user files, and its own
+/// `conf.d` is used. Only existing files are listed, except `explicit`.
+pub fn config_files(explicit: Option<&Path>) -> Vec<PathBuf> {
+    let (mut files, main_dir) = match explicit {
+        Some(file) => (vec![file.to_path_buf()], file.parent().map(Path::to_path_buf)),
+        None => {
+            let user_dir = user_config_dir();
+            let mut user_file = user_dir.join("config.toml");
+            if !user_file.is_file() {
+                user_file = legacy_config_file();
+            }
+            let files = system_config_dirs()
+                .into_iter()
+                .rev()
+                .map(|dir| dir.join("config.toml"))
+                .chain([user_file])
+                .filter(|file| file.is_file())
+                .collect();
+            (files, Some(user_dir))
+        }
+    };
+    if let Some(drop_in_dir) = main_dir.map(|dir| dir.join("conf.d"))
+        && let Ok(entries) = fs::read_dir(&drop_in_dir)
+    {
+        let mut drop_ins: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        drop_ins.sort();
+        files.extend(drop_ins);
+    }
+    files
+}
+
+/// Merges `overlay` into `base`: tables are merged key by key, anything else is replaced.
+pub fn merge_values(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Reads `files` and merges them in order into a single table.
+pub fn merge_config_files(files: &[PathBuf]) -> Result<toml::Value, String> {
+    read_config_files(files).map(|(merged, _)| merged)
+}
+
+/// Like `merge_config_files`, also giving every file read, the included ones too, in the
+/// order they were read.
+pub fn read_config_files(files: &[PathBuf]) -> Result<(toml::Value, Vec<PathBuf>), String> {
+    let mut merged = toml::Value::Table(toml::value::Table::new());
+    let mut read = Vec::new();
+    for file in files {
+        merge_values(&mut merged, read_with_includes(file, &mut Vec::new(), &mut read)?);
+    }
+    Ok((merged, read))
+}
+
+/// Reads `file` with the files listed in its top-level `include` array merged in first,
+/// so the including file overrides them. Relative paths are relative to the directory of
+/// the including file. `including` holds the files being read, to report include cycles.
+fn read_with_includes(
+    file: &Path,
+    including: &mut Vec<PathBuf>,
+    read: &mut Vec<PathBuf>,
+) -> Result<toml::Value, String> {
+    let canonical = fs::canonicalize(file)
+        .map_err(|e| format!("Failed to read configuration file {:?}: {}", file, e))?;
+    if including.contains(&canonical) {
+        return Err(format!("Configuration file {:?} includes itself", canonical));
+    }
+    let text = fs::read_to_string(file)
+        .map_err(|e| format!("Failed to read configuration file {:?}: {}", file, e))?;
+    read.push(file.to_path_buf());
+    let mut value: toml::Value = toml::from_str(&text)
+        .map_err(|e| format!("Failed to parse configuration file {:?}: {}", file, e))?;
+    let includes = match value.as_table_mut().and_then(|table| table.remove("include")) {
+        None => Vec::new(),
+        Some(toml::Value::Array(includes)) => includes,
+        Some(_) => return Err(format!("`include` in {:?} must be an array of paths", file)),
+    };
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let mut merged = toml::Value::Table(toml::value::Table::new());
+    including.push(canonical);
+    for include in includes {
+        let Some(include) = include.as_str() else {
+            return Err(format!("`include` in {:?} must be an array of paths", file));
+        };
+        let included = read_with_includes(&dir.join(expand_path(include)), including, read)?;
+        merge_values(&mut merged, included);
+    }
+    including.pop();
+    merge_values(&mut merged, value);
+    Ok(merged)
+}
+
+/// Applies the `GCN_<SECTION>_<KEY>` variables among `vars` to `value`, as in
+/// `GCN_GRID_ROWS=8` or `GCN_THEME_FONT_WEIGHT=bold`. Values are read as TOML (so arrays
+/// are written `[1, 2]`), except for settings whose default is a string, which take the
+/// variable as is. Variables naming no known setting are reported and ignored.
+pub fn apply_env_overrides(value: &mut toml::Value, vars: impl IntoIterator<Item = (String, String)>) {
+    let defaults = toml::Value::try_from(Conf::default()).expect("The default configuration serializes");
+    let Some(table) = value.as_table_mut() else {
+        return;
+    };
+    for (name, raw) in vars {
+        let Some(rest) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let rest = rest.to_lowercase();
+        // Section names may contain underscores as well, so try every split.
+        let found = rest.match_indices('_').find_map(|(at, _)| {
+            let (section, key) = (&rest[..at], &rest[at + 1..]);
+            let default = defaults.get(section)?.get(key)?;
+            (section != "profiles").then_some((section, key, default))
+        });
+        let Some((section, key, default)) = found else {
+            log::warn!("Ignoring {}, which names no known setting.", name);
+            continue;
+        };
+        let setting = match default {
+            toml::Value::String(_) => toml::Value::String(raw),
+            _ => toml::from_str::<toml::value::Table>(&format!("v = {}", raw))
+                .ok()
+                .and_then(|mut parsed| parsed.remove("v"))
+                .unwrap_or(toml::Value::String(raw)),
+        };
+        let section = table
+            .entry(section.to_string())
+            .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+        if let Some(section) = section.as_table_mut() {
+            section.insert(key.to_string(), setting);
+        }
+    }
+}
+
+/// The configuration in effect: the defaults, overridden by `files` in order, then by
+/// `GCN_` environment variables. Also gives every file read, see `read_config_files`.
+pub fn load_config(files: &[PathBuf]) -> Result<(Conf, Vec<PathBuf>), String> {
+    let (mut value, read) = read_config_files(files)?;
+    apply_env_overrides(&mut value, env::vars());
+    let conf = value.try_into().map_err(|e| format!("Invalid configuration: {}", e))?;
+    Ok((conf, read))
+}
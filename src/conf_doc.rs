@@ -0,0 +1,135 @@
+//! Documentation of the configuration, as a commented config file.
+//!
+//! The file is generated from the JSON Schema of `Conf`, so the descriptions, defaults and
+//! enum values of the settings come from the structs' doc comments and `Default`
+//! implementations and can't drift from the code.
+
+use crate::schema::conf_schema;
+use serde_json::Value;
+
+/// The profile shown, commented out, in the generated config file.
+const PROFILE_EXAMPLE: &str = r#"[profiles.browser]
+app_id = "^(firefox|chromium)$"
+grid = { rows = 6, columns = 8 }
+theme = { font_size = 24 }"#;
+
+/// The default configuration as a config file, with every setting preceded by its
+/// documentation and, for enums, the values it takes.
+pub fn commented_default() -> String {
+    let schema = conf_schema();
+    let mut out = String::from(
+        "# Configuration of gtk-cursor-navigator, shared by the server and the client.\n\
+         # Every setting is shown with its default value. Other files can be merged in with a\n\
+         # top-level `include = [\"other.toml\"]`, relative to this file.\n",
+    );
+    let Some(sections) = schema["properties"].as_object() else {
+        return out;
+    };
+    for (name, section) in sections {
+        let definition = resolve(&schema, section);
+        if let Some(fields) = definition["properties"].as_object() {
+            out.push('\n');
+            push_comment(&mut out, description(definition).or(description(section)));
+            out.push_str(&format!("[{}]\n", name));
+            for (key, field) in fields {
+                push_comment(&mut out, description(field));
+                if let Some(values) = resolve(&schema, field)["enum"].as_array() {
+                    let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                    push_comment(&mut out, Some(&format!("One of {}.", values.join(", "))));
+                }
+                if let Some(value) = toml_value(&field["default"]) {
+                    out.push_str(&format!("{} = {}\n", key, value));
+                }
+            }
+        } else if section["additionalProperties"].is_object() {
+            let entry = resolve(&schema, &section["additionalProperties"]);
+            out.push('\n');
+            push_comment(&mut out, description(section));
+            push_comment(&mut out, description(entry));
+            for line in PROFILE_EXAMPLE.lines() {
+                let key = line.split(" = ").next().unwrap_or_default();
+                push_comment(&mut out, description(&entry["properties"][key]));
+                push_comment(&mut out, Some(line));
+            }
+        } else if let Some(value) = toml_value(&section["default"]) {
+            out.push('\n');
+            push_comment(&mut out, description(section));
+            out.push_str(&format!("{} = {}\n", name, value));
+        }
+    }
+    out
+}
+
+/// The definition a property refers to, or the property itself.
+fn resolve<'a>(schema: &'a Value, property: &'a Value) -> &'a Value {
+    match property["$ref"].as_str().and_then(|path| path.strip_prefix("#/$defs/")) {
+        Some(name) => &schema["$defs"][name],
+        None => property,
+    }
+}
+
+fn description(property: &Value) -> Option<&str> {
+    property["description"].as_str()
+}
+
+/// `value` written as a TOML value, or `None` for a missing default.
+fn toml_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Array(values) => {
+            let values: Vec<String> = values.iter().filter_map(toml_value).collect();
+            Some(format!("[{}]", values.join(", ")))
+        }
+        Value::Object(table) => {
+            let entries: Vec<String> = table
+                .iter()
+                .filter_map(|(key, value)| Some(format!("{} = {}", key, toml_value(value)?)))
+                .collect();
+            Some(format!("{{ {} }}", entries.join(", ")))
+        }
+        value => Some(value.to_string()),
+    }
+}
+
+/// Comment lines are wrapped to this width.
+const COMMENT_WIDTH: usize = 90;
+
+/// Writes `text` as comment lines. The lines of a doc comment are joined back into
+/// paragraphs and bullets before being wrapped.
+fn push_comment(out: &mut String, text: Option<&str>) {
+    let Some(text) = text else {
+        return;
+    };
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut joined = true;
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() {
+            paragraphs.push(String::new());
+            joined = false;
+        } else if let Some(last) = paragraphs.last_mut().filter(|_| joined && !line.starts_with("- ")) {
+            last.push(' ');
+            last.push_str(line);
+        } else {
+            paragraphs.push(line.to_string());
+            joined = true;
+        }
+    }
+    for paragraph in paragraphs {
+        if paragraph.is_empty() {
+            out.push_str("#\n");
+            continue;
+        }
+        let mut wrapped = String::from("#");
+        for word in paragraph.split(' ') {
+            if wrapped.len() > 2 && wrapped.len() + 1 + word.len() > COMMENT_WIDTH {
+                out.push_str(&wrapped);
+                out.push('\n');
+                wrapped = String::from("#");
+            }
+            wrapped.push(' ');
+            wrapped.push_str(word);
+        }
+        out.push_str(&wrapped);
+        out.push('\n');
+    }
+}
@@ -0,0 +1,193 @@
+//! Keeping a single server running per user session.
+//!
+//! The server holds an exclusive lock on a pid file under `$XDG_RUNTIME_DIR` for as long
+//! as it runs, so a second instance finds out before failing to bind its port, and can
+//! tell which process to stop when asked to replace it.
+//!
+//! When run as a systemd user service, the server takes its listening socket from the
+//! socket unit (`LISTEN_FDS`) and reports readiness on `NOTIFY_SOCKET`, so the first
+//! client connection starts it on demand.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::net::TcpListener;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A locked pid file, removed when dropped.
+pub struct PidFile {
+    path: PathBuf,
+    // Keeps the lock, which is released when the file is closed.
+    _file: File,
+}
+
+impl PidFile {
+    /// `$XDG_RUNTIME_DIR/<name>.pid`, or a file in the temporary directory without it.
+    pub fn default_path(name: &str) -> PathBuf {
+        env::var_os("XDG_RUNTIME_DIR")
+            .filter(|dir| !dir.is_empty())
+            .map_or_else(env::temp_dir, PathBuf::from)
+            .join(format!("{}.pid", name))
+    }
+
+    /// Locks the pid file at `path` and writes the id of this process to it.
+    /// Fails with `WouldBlock` when another process holds the lock.
+    pub fn lock(path: &Path) -> io::Result<PidFile> {
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?;
+            // SAFETY: the descriptor belongs to `file`, which outlives the call.
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // The previous holder removes the file before releasing the lock, so the
+            // lock may be on a file that is gone by now; start over with a fresh one.
+            let same_file = fs::metadata(path)
+                .is_ok_and(|metadata| metadata.ino() == file.metadata().map_or(0, |m| m.ino()));
+            if !same_file {
+                continue;
+            }
+            file.set_len(0)?;
+            file.rewind()?;
+            writeln!(file, "{}", std::process::id())?;
+            return Ok(PidFile { path: path.to_path_buf(), _file: file });
+        }
+    }
+
+    /// Like `lock`, but stops the process holding the lock with SIGTERM first and waits
+    /// up to `timeout` for it to let go.
+    pub fn take_over(path: &Path, timeout: Duration) -> io::Result<PidFile> {
+        let deadline = Instant::now() + timeout;
+        let mut signalled = false;
+        loop {
+            match PidFile::lock(path) {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock && Instant::now() < deadline => {
+                    if !signalled && let Some(pid) = running_pid(path) {
+                        // SAFETY: kill has no memory safety requirements.
+                        unsafe { libc::kill(pid, libc::SIGTERM) };
+                        signalled = true;
+                    }
+                    thread::sleep(Duration::from_millis(100));
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The process id written to the pid file at `path`, if any.
+pub fn running_pid(path: &Path) -> Option<i32> {
+    let mut contents = String::new();
+    File::open(path).ok()?.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
+
+/// The first file descriptor passed by socket activation, see `sd_listen_fds(3)`.
+const LISTEN_FDS_START: i32 = 3;
+
+/// Takes the listening socket passed by systemd socket activation, if any.
+/// Only the first socket is used; the unit is expected to declare a single `ListenStream`.
+pub fn activation_listener() -> Option<TcpListener> {
+    let for_us = env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let count = env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<i32>().ok())
+        .unwrap_or(0);
+    if !for_us || count < 1 {
+        return None;
+    }
+    // SAFETY: systemd hands over descriptors starting at 3 that nothing else owns, and
+    // the flag keeps them from leaking into spawned processes.
+    unsafe {
+        libc::fcntl(LISTEN_FDS_START, libc::F_SETFD, libc::FD_CLOEXEC);
+        Some(TcpListener::from_raw_fd(LISTEN_FDS_START))
+    }
+}
+
+/// Sends `state` (e.g. "READY=1") to the service manager, if it asked for notifications
+/// through `NOTIFY_SOCKET`, see `sd_notify(3)`.
+pub fn notify(state: &str) -> io::Result<()> {
+    match env::var("NOTIFY_SOCKET") {
+        Ok(socket) if !socket.is_empty() => notify_socket(&socket, state),
+        _ => Ok(()),
+    }
+}
+
+/// Sends `state` to the notification socket at `socket`, where a leading '@' stands for
+/// the abstract namespace.
+pub fn notify_socket(socket: &str, state: &str) -> io::Result<()> {
+    let addr = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+    let sender = UnixDatagram::unbound()?;
+    sender.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(())
+}
+
+/// The systemd socket unit listening on `addr` for the server.
+pub fn socket_unit(name: &str, addr: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Socket of {name}\n\
+         \n\
+         [Socket]\n\
+         ListenStream={addr}\n\
+         \n\
+         [Install]\n\
+         WantedBy=sockets.target\n"
+    )
+}
+
+/// The systemd service unit starting the server at `exe` with `args`, activated by the
+/// socket unit of the same name.
+pub fn service_unit(name: &str, exe: &Path, args: &[String]) -> String {
+    let mut command = quote_unit_arg(&exe.to_string_lossy());
+    for arg in args {
+        command.push(' ');
+        command.push_str(&quote_unit_arg(arg));
+    }
+    format!(
+        "[Unit]\n\
+         Description={name}\n\
+         Requires={name}.socket\n\
+         After={name}.socket\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         ExecStart={command}\n\
+         Restart=on-failure\n"
+    )
+}
+
+/// Quotes an `ExecStart` argument when needed, see `systemd.service(5)`.
+fn quote_unit_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || "\"'\\;$%".contains(c)) {
+        return arg.to_string();
+    }
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "$$")
+        .replace('%', "%%");
+    format!("\"{}\"", escaped)
+}
@@ -0,0 +1,168 @@
+//! Queries for the focused window, used to pick the grid profile matching it and to
+//! place the grid over it in the window scope.
+//!
+//! Wayland has no common way for a client to learn what is focused, so each compositor
+//! family gets its own query: sway and Hyprland through their IPC tools, and other
+//! wlroots compositors through the foreign-toplevel protocol as listed by `lswt`.
+
+use crate::geometry::Rect;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::io;
+use std::process::Command;
+
+/// The focused window as far as profiles and the window scope are concerned.
+/// `app_id` is the Wayland app id, or the window class for Xwayland windows.
+/// `rect` is the window's geometry relative to the output showing it, when known.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct FocusedWindow {
+    pub app_id: String,
+    pub title: String,
+    #[serde(default)]
+    pub rect: Option<Rect>,
+}
+
+/// A way of finding the focused window. `Ok(None)` means nothing is focused.
+pub trait FocusQuery {
+    fn focused_window(&self) -> io::Result<Option<FocusedWindow>>;
+}
+
+/// Queries sway through `swaymsg -t get_tree`.
+pub struct Sway;
+
+/// Queries Hyprland through `hyprctl activewindow -j` and `hyprctl monitors -j`.
+pub struct Hyprland;
+
+/// Queries any compositor implementing wlr-foreign-toplevel-management through `lswt -j`.
+/// The protocol doesn't expose window geometry, so no rectangle is reported.
+pub struct ForeignToplevel;
+
+impl FocusQuery for Sway {
+    fn focused_window(&self) -> io::Result<Option<FocusedWindow>> {
+        parse_sway_tree(&run_json("swaymsg", &["-t", "get_tree", "-r"])?)
+    }
+}
+
+impl FocusQuery for Hyprland {
+    fn focused_window(&self) -> io::Result<Option<FocusedWindow>> {
+        let window = run_json("hyprctl", &["activewindow", "-j"])?;
+        let monitors = run_json("hyprctl", &["monitors", "-j"])?;
+        parse_hyprland_window(&window, &monitors)
+    }
+}
+
+impl FocusQuery for ForeignToplevel {
+    fn focused_window(&self) -> io::Result<Option<FocusedWindow>> {
+        parse_lswt(&run_json("lswt", &["-j"])?)
+    }
+}
+
+/// Picks a query by name: "sway", "hyprland", "toplevel", or "auto", which goes by the
+/// environment the compositor sets and falls back to the foreign-toplevel query.
+/// Returns `None` for "none" and unknown names.
+pub fn query_by_name(name: &str) -> Option<Box<dyn FocusQuery>> {
+    match name {
+        "sway" => Some(Box::new(Sway)),
+        "hyprland" => Some(Box::new(Hyprland)),
+        "toplevel" => Some(Box::new(ForeignToplevel)),
+        "auto" if env::var_os("SWAYSOCK").is_some() => Some(Box::new(Sway)),
+        "auto" if env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() => Some(Box::new(Hyprland)),
+        "auto" => Some(Box::new(ForeignToplevel)),
+        _ => None,
+    }
+}
+
+fn run_json(program: &str, args: &[&str]) -> io::Result<Value> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("{} failed: {}", program, output.status)));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Reads a `{x, y, width, height}` object, shifted by `origin`.
+fn rect_from(value: &Value, origin: (f64, f64)) -> Option<Rect> {
+    Some(Rect::new(
+        value["x"].as_f64()? - origin.0,
+        value["y"].as_f64()? - origin.1,
+        value["width"].as_f64()?,
+        value["height"].as_f64()?,
+    ))
+}
+
+/// Finds the focused view in a sway tree. Xwayland views have no app id but a class.
+/// Sway reports rectangles in layout coordinates, so the view's rectangle is made
+/// relative to the output containing it.
+pub fn parse_sway_tree(tree: &Value) -> io::Result<Option<FocusedWindow>> {
+    let mut stack = vec![(tree, (0.0, 0.0))];
+    while let Some((node, origin)) = stack.pop() {
+        if node["focused"].as_bool() == Some(true) && node["type"] != "workspace" {
+            let app_id = node["app_id"]
+                .as_str()
+                .or_else(|| node["window_properties"]["class"].as_str())
+                .unwrap_or_default();
+            return Ok(Some(FocusedWindow {
+                app_id: app_id.to_string(),
+                title: node["name"].as_str().unwrap_or_default().to_string(),
+                rect: rect_from(&node["rect"], origin),
+            }));
+        }
+        let origin = if node["type"] == "output" {
+            (node["rect"]["x"].as_f64().unwrap_or(0.0), node["rect"]["y"].as_f64().unwrap_or(0.0))
+        } else {
+            origin
+        };
+        for children in ["nodes", "floating_nodes"] {
+            if let Some(children) = node[children].as_array() {
+                stack.extend(children.iter().map(|child| (child, origin)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the active window reported by Hyprland, which is an empty object when
+/// nothing is focused. Its position is made relative to its monitor from `monitors`.
+pub fn parse_hyprland_window(window: &Value, monitors: &Value) -> io::Result<Option<FocusedWindow>> {
+    let Some(class) = window["class"].as_str() else {
+        return Ok(None);
+    };
+    let monitor = monitors
+        .as_array()
+        .and_then(|monitors| monitors.iter().find(|m| m["id"] == window["monitor"]));
+    let origin = monitor.map_or((0.0, 0.0), |m| {
+        (m["x"].as_f64().unwrap_or(0.0), m["y"].as_f64().unwrap_or(0.0))
+    });
+    let rect = match (&window["at"], &window["size"]) {
+        (Value::Array(at), Value::Array(size)) if at.len() == 2 && size.len() == 2 => {
+            Some(Rect::new(
+                at[0].as_f64().unwrap_or(0.0) - origin.0,
+                at[1].as_f64().unwrap_or(0.0) - origin.1,
+                size[0].as_f64().unwrap_or(0.0),
+                size[1].as_f64().unwrap_or(0.0),
+            ))
+        }
+        _ => None,
+    };
+    Ok(Some(FocusedWindow {
+        app_id: class.to_string(),
+        title: window["title"].as_str().unwrap_or_default().to_string(),
+        rect,
+    }))
+}
+
+/// Finds the activated toplevel in the output of `lswt -j`.
+pub fn parse_lswt(list: &Value) -> io::Result<Option<FocusedWindow>> {
+    let toplevels = list["toplevels"]
+        .as_array()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "lswt: no toplevels"))?;
+    Ok(toplevels
+        .iter()
+        .find(|toplevel| toplevel["activated"].as_bool() == Some(true))
+        .map(|toplevel| FocusedWindow {
+            app_id: toplevel["app-id"].as_str().unwrap_or_default().to_string(),
+            title: toplevel["title"].as_str().unwrap_or_default().to_string(),
+            rect: None,
+        }))
+}
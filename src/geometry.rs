@@ -0,0 +1,529 @@
+//! Geometry of the overlay, computed without GTK so it can be tested on its own.
+//!
+//! Sizes are in logical pixels of the overlay surface.
+
+use crate::conf::{ConfGrid, ConfTheme, FontSizing, Length, ReservedNotCovered};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Width of a label glyph relative to the font size. Tokens are capitals, digits and
+/// punctuation, which stay below this in common bold sans-serif fonts.
+pub const GLYPH_WIDTH_EM: f64 = 0.75;
+/// Height of a line of text relative to the font size.
+pub const LINE_HEIGHT_EM: f64 = 1.3;
+/// Padding in pixels on the left and right of the `.label-pill` box around a label.
+pub const LABEL_PILL_PADDING: u32 = 2;
+
+/// A rectangle with its origin at the top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Rect {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Rect { x, y, width, height }
+    }
+
+    pub fn center(&self) -> (f64, f64) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    /// Whether the point lies within the rectangle; the right and bottom edges belong to
+    /// the neighbouring rectangles.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    pub fn translate(&self, dx: f64, dy: f64) -> Rect {
+        Rect::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+
+    /// The part shared with `other`, which is empty (zero-sized) when they don't overlap.
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Rect::new(x, y, (right - x).max(0.0), (bottom - y).max(0.0))
+    }
+
+    /// The pixel nearest to `(x, y)` within the rectangle, so the pointer can't be moved
+    /// past its right or bottom edge.
+    pub fn clamp_pixel(&self, x: i32, y: i32) -> (i32, i32) {
+        let (left, top) = (self.x.floor() as i32, self.y.floor() as i32);
+        let right = (self.x + self.width).ceil() as i32 - 1;
+        let bottom = (self.y + self.height).ceil() as i32 - 1;
+        (x.min(right).max(left), y.min(bottom).max(top))
+    }
+
+    /// The center rounded to whole pixels, where the pointer is moved to.
+    pub fn pixel_center(&self) -> (i32, i32) {
+        let (x, y) = self.center();
+        (x.round() as i32, y.round() as i32)
+    }
+
+    /// Formats the rectangle with `slurp`-like placeholders: `%x`, `%y`, `%w` and `%h` for
+    /// the rectangle, `%X` and `%Y` for its pixel center, and `%%` for a percent sign.
+    /// Values are whole pixels, so `"%x,%y %wx%h"` can be passed to `grim -g`.
+    pub fn format(&self, format: &str) -> String {
+        let (center_x, center_y) = self.pixel_center();
+        let mut out = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('x') => out.push_str(&(self.x.round() as i32).to_string()),
+                Some('y') => out.push_str(&(self.y.round() as i32).to_string()),
+                Some('w') => out.push_str(&(self.width.round() as i32).to_string()),
+                Some('h') => out.push_str(&(self.height.round() as i32).to_string()),
+                Some('X') => out.push_str(&center_x.to_string()),
+                Some('Y') => out.push_str(&center_y.to_string()),
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                }
+                None => out.push('%'),
+            }
+        }
+        out
+    }
+}
+
+/// Parses `x,y,width,height`, as given on the command line.
+impl FromStr for Rect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f64> = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid rectangle {:?}: {}", s, e))?;
+        match values[..] {
+            [x, y, width, height] if width > 0.0 && height > 0.0 => {
+                Ok(Rect::new(x, y, width, height))
+            }
+            _ => Err(format!("Invalid rectangle {:?}: expected x,y,width,height", s)),
+        }
+    }
+}
+
+/// Parses a region as `slurp` prints it, `x,y wxh`. A comma may stand in for the space,
+/// which is easier to pass as a single argument. Surrounding whitespace, like the newline
+/// ending slurp's output, is ignored.
+pub fn parse_region(s: &str) -> Result<Rect, String> {
+    let invalid = || format!("Invalid region {:?}: expected x,y wxh", s);
+    let (position, size) = s
+        .trim()
+        .rsplit_once([' ', ','])
+        .ok_or_else(invalid)?;
+    let (x, y) = position.trim().split_once(',').ok_or_else(invalid)?;
+    let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+    let number = |value: &str| value.trim().parse::<f64>().map_err(|_| invalid());
+    let rect = Rect::new(number(x)?, number(y)?, number(width)?, number(height)?);
+    if rect.width > 0.0 && rect.height > 0.0 {
+        Ok(rect)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Finds the output showing `region`, given in global layout coordinates as `slurp` prints
+/// it, among `outputs`. Gives the output's index and the region relative to that output,
+/// as `OverlayGeometry` takes its scope; `None` when no output contains the region's
+/// center.
+pub fn region_on_output(region: Rect, outputs: &[Rect]) -> Option<(usize, Rect)> {
+    let (x, y) = region.center();
+    let index = outputs.iter().position(|output| output.contains(x, y))?;
+    let output = outputs[index];
+    Some((index, region.translate(-output.x, -output.y)))
+}
+
+/// Splits `length` into `parts` integer lengths differing by at most one pixel, the way a
+/// homogeneous `GtkGrid` does. The lengths add up to `length` exactly.
+pub fn split_length(length: u32, parts: u32) -> Vec<u32> {
+    if parts == 0 {
+        return Vec::new();
+    }
+    let base = length / parts;
+    let extra = length % parts;
+    (0..parts).map(|i| base + u32::from(i < extra)).collect()
+}
+
+/// The offsets of the boundaries between `parts` tracks splitting `length`, including both
+/// outer edges, so there is one more offset than there are tracks.
+pub fn track_offsets(length: u32, parts: u32) -> Vec<u32> {
+    let mut offsets = vec![0];
+    let mut offset = 0;
+    for size in split_length(length, parts) {
+        offset += size;
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// The cells of a `rows` × `columns` grid covering `width` × `height`, row-major.
+/// Cells share their boundaries with their neighbours, so they neither overlap nor leave gaps.
+pub fn grid_cells(width: u32, height: u32, rows: u32, columns: u32) -> Vec<Rect> {
+    let xs = track_offsets(width, columns);
+    let ys = track_offsets(height, rows);
+    let mut cells = Vec::with_capacity((rows * columns) as usize);
+    for row in ys.windows(2) {
+        for column in xs.windows(2) {
+            cells.push(Rect::new(
+                column[0] as f64,
+                row[0] as f64,
+                (column[1] - column[0]) as f64,
+                (row[1] - row[0]) as f64,
+            ));
+        }
+    }
+    cells
+}
+
+/// The row-major index of the cell of a `rows` × `columns` grid covering `width` × `height`
+/// that contains the point, or `None` when the point lies outside the grid.
+/// This is the hit test of `grid_cells`, found by searching the boundaries instead of
+/// scanning every cell.
+pub fn cell_at(width: u32, height: u32, rows: u32, columns: u32, x: f64, y: f64) -> Option<usize> {
+    let column = track_at(&track_offsets(width, columns), x)?;
+    let row = track_at(&track_offsets(height, rows), y)?;
+    Some(row * columns as usize + column)
+}
+
+/// The index of the track containing `position`, given the track boundaries.
+fn track_at(offsets: &[u32], position: f64) -> Option<usize> {
+    let start = *offsets.first()? as f64;
+    let end = *offsets.last()? as f64;
+    if offsets.len() < 2 || position < start || position >= end {
+        return None;
+    }
+    // The first boundary above the position closes its track.
+    Some(offsets.partition_point(|&offset| offset as f64 <= position) - 1)
+}
+
+/// The offsets of the boundaries of tracks sized by `sizes` within `length`, including
+/// both outer edges. Pixel sizes are taken as they are, and fractional sizes share what
+/// remains in proportion to each other, rounded so that they end exactly at `length`.
+/// Without fractional sizes the tracks may end before `length`; tracks past it are cut.
+pub fn sized_track_offsets(length: u32, sizes: &[Length]) -> Vec<u32> {
+    let pixels: u32 = sizes
+        .iter()
+        .map(|size| match size {
+            Length::Pixels(pixels) => *pixels,
+            Length::Fraction(_) => 0,
+        })
+        .sum();
+    let fractions: f64 = sizes
+        .iter()
+        .map(|size| match size {
+            Length::Pixels(_) => 0.0,
+            Length::Fraction(fraction) => fraction.max(0.0),
+        })
+        .sum();
+    let rest = length.saturating_sub(pixels) as f64;
+
+    let mut offsets = vec![0];
+    let (mut pixels_before, mut fractions_before) = (0, 0.0);
+    for size in sizes {
+        match size {
+            Length::Pixels(pixels) => pixels_before += pixels,
+            Length::Fraction(fraction) => fractions_before += fraction.max(0.0),
+        }
+        // Rounding the running total instead of every track keeps the sum exact.
+        let shared = if fractions > 0.0 {
+            (rest * fractions_before / fractions).round() as u32
+        } else {
+            0
+        };
+        offsets.push((pixels_before + shared).min(length));
+    }
+    offsets
+}
+
+/// Resolves a region bound against the overlay `length`.
+fn resolve_length(size: Length, length: u32) -> u32 {
+    match size {
+        Length::Pixels(pixels) => pixels.min(length),
+        Length::Fraction(fraction) => ((length as f64 * fraction).round().max(0.0) as u32).min(length),
+    }
+}
+
+/// One region of a laid out grid, given by the absolute offsets of its column and row
+/// boundaries, outer edges included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionLayout {
+    pub xs: Vec<u32>,
+    pub ys: Vec<u32>,
+}
+
+impl RegionLayout {
+    pub fn bounds(&self) -> Rect {
+        let (x, y) = (self.xs[0], self.ys[0]);
+        let (right, bottom) = (*self.xs.last().unwrap(), *self.ys.last().unwrap());
+        Rect::new(x as f64, y as f64, (right - x) as f64, (bottom - y) as f64)
+    }
+
+    fn cell_at(&self, x: f64, y: f64) -> Option<usize> {
+        let column = track_at(&self.xs, x)?;
+        let row = track_at(&self.ys, y)?;
+        Some(row * (self.xs.len() - 1) + column)
+    }
+}
+
+/// The cells of a grid, possibly split into regions with rows and columns of their own
+/// sizes, laid out over an overlay of `width` × `height`. Cells are numbered region by
+/// region, row-major within each, which is the order tokens are assigned in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridLayout {
+    pub width: u32,
+    pub height: u32,
+    pub regions: Vec<RegionLayout>,
+}
+
+impl GridLayout {
+    pub fn new(width: u32, height: u32, grid: &ConfGrid) -> Self {
+        Self::with_scale(width, height, 1.0, grid)
+    }
+
+    /// Lays out the grid over `width` × `height` device pixels of an output scaled by
+    /// `scale`, so that boundaries fall on device pixels. Pixel sizes in the configuration
+    /// are logical pixels and are scaled accordingly.
+    pub fn with_scale(width: u32, height: u32, scale: f64, grid: &ConfGrid) -> Self {
+        let scaled = |size: Length| match size {
+            Length::Pixels(pixels) => Length::Pixels((pixels as f64 * scale).round() as u32),
+            fraction => fraction,
+        };
+        let regions = grid
+            .effective_regions()
+            .iter()
+            .map(|region| {
+                let x = resolve_length(scaled(region.x), width);
+                let y = resolve_length(scaled(region.y), height);
+                // Sizes are fractions of the whole overlay, cut where they would cross its edge.
+                let region_width = resolve_length(scaled(region.width), width).min(width - x);
+                let region_height = resolve_length(scaled(region.height), height).min(height - y);
+                let offsets = |length: u32, count: usize, sizes: &[Length], start: u32| {
+                    let offsets = if sizes.is_empty() {
+                        track_offsets(length, count as u32)
+                    } else {
+                        let sizes: Vec<Length> = sizes.iter().copied().map(scaled).collect();
+                        sized_track_offsets(length, &sizes)
+                    };
+                    offsets.into_iter().map(|offset| start + offset).collect()
+                };
+                RegionLayout {
+                    xs: offsets(region_width, region.column_count(), &region.column_sizes, x),
+                    ys: offsets(region_height, region.row_count(), &region.row_sizes, y),
+                }
+            })
+            .collect();
+        GridLayout { width, height, regions }
+    }
+
+    /// The cells of all regions, in token order.
+    pub fn cells(&self) -> Vec<Rect> {
+        let mut cells = Vec::new();
+        for region in &self.regions {
+            for row in region.ys.windows(2) {
+                for column in region.xs.windows(2) {
+                    cells.push(Rect::new(
+                        column[0] as f64,
+                        row[0] as f64,
+                        (column[1] - column[0]) as f64,
+                        (row[1] - row[0]) as f64,
+                    ));
+                }
+            }
+        }
+        cells
+    }
+
+    /// The index of the cell containing the point. Where regions overlap, the one listed
+    /// first wins.
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<usize> {
+        let mut first = 0;
+        for region in &self.regions {
+            if let Some(index) = region.cell_at(x, y) {
+                return Some(first + index);
+            }
+            first += (region.xs.len() - 1) * (region.ys.len() - 1);
+        }
+        None
+    }
+
+    /// The width of the narrowest and the height of the lowest track over all regions,
+    /// which bounds the label font size.
+    pub fn min_cell_size(&self) -> (u32, u32) {
+        let narrowest = |offsets: &[u32]| offsets.windows(2).map(|t| t[1] - t[0]).min();
+        let width = self.regions.iter().filter_map(|r| narrowest(&r.xs)).min().unwrap_or(0);
+        let height = self.regions.iter().filter_map(|r| narrowest(&r.ys)).min().unwrap_or(0);
+        (width, height)
+    }
+}
+
+/// A cell of the grid placed on the output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlacedCell {
+    /// The cell in global layout coordinates, in logical pixels.
+    pub rect: Rect,
+    /// Where the pointer goes when the cell is selected: its center in whole pixels.
+    pub center: (i32, i32),
+}
+
+/// Where the grid lies on an output, computed from the output alone so it can be tested
+/// without a compositor. The overlay surface covers the output minus the reserved edges,
+/// or the scope when one is given, clamped to that area. The cells cover the surface past
+/// the header strips, and are laid out in device pixels so lines stay crisp when scaled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlayGeometry {
+    /// The output in global layout coordinates, in logical pixels.
+    pub output: Rect,
+    /// Device pixels per logical pixel.
+    pub scale: f64,
+    /// The overlay surface, relative to the output.
+    pub surface: Rect,
+    /// The part of the surface covered by cells, relative to the surface.
+    pub grid_area: Rect,
+    /// The layout of the cells over the grid area, in device pixels.
+    pub layout: GridLayout,
+}
+
+impl OverlayGeometry {
+    /// `header_strip` is the size of the row and column header strips, or zero without
+    /// headers. `scope` is relative to the output.
+    pub fn new(
+        output: Rect,
+        scale: f64,
+        reserved: &ReservedNotCovered,
+        scope: Option<Rect>,
+        grid: &ConfGrid,
+        header_strip: f64,
+    ) -> Self {
+        let scale = if scale > 0.0 { scale } else { 1.0 };
+        let usable = Rect::new(
+            reserved.left as f64,
+            reserved.top as f64,
+            (output.width - reserved.left as f64 - reserved.right as f64).max(0.0),
+            (output.height - reserved.top as f64 - reserved.bottom as f64).max(0.0),
+        );
+        let surface = match scope {
+            Some(scope) => scope.intersect(&usable),
+            None => usable,
+        };
+        let strip = header_strip.min(surface.width).min(surface.height).max(0.0);
+        let grid_area = Rect::new(strip, strip, surface.width - strip, surface.height - strip);
+        let layout = GridLayout::with_scale(
+            (grid_area.width * scale).round() as u32,
+            (grid_area.height * scale).round() as u32,
+            scale,
+            grid,
+        );
+        OverlayGeometry { output, scale, surface, grid_area, layout }
+    }
+
+    /// Where the surface starts in global layout coordinates.
+    pub fn surface_origin(&self) -> (f64, f64) {
+        (self.output.x + self.surface.x, self.output.y + self.surface.y)
+    }
+
+    /// The cells relative to the grid area, in logical pixels, as the renderers place them.
+    pub fn local_cells(&self) -> Vec<Rect> {
+        let scale = self.scale;
+        self.layout
+            .cells()
+            .iter()
+            .map(|cell| Rect::new(cell.x / scale, cell.y / scale, cell.width / scale, cell.height / scale))
+            .collect()
+    }
+
+    /// The cells on the output, in token order.
+    pub fn cells(&self) -> Vec<PlacedCell> {
+        let (origin_x, origin_y) = self.surface_origin();
+        self.local_cells()
+            .iter()
+            .map(|cell| {
+                let rect = cell.translate(origin_x + self.grid_area.x, origin_y + self.grid_area.y);
+                PlacedCell { rect, center: rect.pixel_center() }
+            })
+            .collect()
+    }
+
+    /// The index of the cell containing a point relative to the grid area.
+    pub fn local_cell_at(&self, x: f64, y: f64) -> Option<usize> {
+        // Snap away the rounding error of going through logical pixels, so a cell's own
+        // corner maps back to it.
+        let device = |value: f64| (value * self.scale * 1e6).round() / 1e6;
+        self.layout.cell_at(device(x), device(y))
+    }
+
+    /// The index of the cell containing a point in global layout coordinates.
+    pub fn cell_at(&self, x: f64, y: f64) -> Option<usize> {
+        let (origin_x, origin_y) = self.surface_origin();
+        self.local_cell_at(x - origin_x - self.grid_area.x, y - origin_y - self.grid_area.y)
+    }
+
+    /// The size of the smallest cell in logical pixels, which bounds the label font size.
+    pub fn min_cell_size(&self) -> (u32, u32) {
+        let (width, height) = self.layout.min_cell_size();
+        ((width as f64 / self.scale) as u32, (height as f64 / self.scale) as u32)
+    }
+}
+
+/// Where a line of `thickness` pixels drawn for the boundary at `offset` starts.
+/// Lines are centered on their boundary but kept within `0..length`, so the outer lines
+/// are as thick as the inner ones instead of being cut in half by the edges.
+pub fn line_start(offset: u32, thickness: u32, length: u32) -> u32 {
+    offset
+        .saturating_sub(thickness / 2)
+        .min(length.saturating_sub(thickness))
+}
+
+/// The size of the smallest cell when an area of `width` × `height` is split into a
+/// `rows` × `columns` grid.
+pub fn min_cell_size(width: u32, height: u32, rows: u32, columns: u32) -> (u32, u32) {
+    (width / columns.max(1), height / rows.max(1))
+}
+
+/// The largest font size whose label of `chars` characters fits into a cell of
+/// `cell_width` × `cell_height`, with room for a border of `line_pixel` on every side and
+/// the padding of its pill on the left and right.
+/// 0 when not even a 1 pixel font fits, in which case the labels are left out.
+pub fn max_fitting_font_size(cell_width: u32, cell_height: u32, chars: usize, line_pixel: u8) -> u32 {
+    let border = 2.0 * line_pixel as f64;
+    let padding = 2.0 * LABEL_PILL_PADDING as f64;
+    let by_width = (cell_width as f64 - border - padding) / (chars.max(1) as f64 * GLYPH_WIDTH_EM);
+    let by_height = (cell_height as f64 - border) / LINE_HEIGHT_EM;
+    by_width.min(by_height).floor().max(0.0) as u32
+}
+
+/// Picks the label font size for cells of `cell_width` × `cell_height` holding labels of
+/// `chars` characters, according to `theme.font_sizing`:
+/// - `Fixed`: `font_size`.
+/// - `Fit`: the largest size that fits, within `font_size_min` and `font_size_max`.
+/// - `Fraction`: `font_size_fraction` of the cell height, within the same bounds.
+///
+/// Whatever the mode, the result never exceeds the size that fits, even when that is
+/// below `font_size_min`; otherwise the labels would push the grid past the output edges.
+/// It is 0 when no size fits.
+pub fn label_font_size(cell_width: u32, cell_height: u32, chars: usize, theme: &ConfTheme) -> u32 {
+    let fitting = max_fitting_font_size(cell_width, cell_height, chars, theme.line_pixel);
+    let min = theme.font_size_min as u32;
+    let max = (theme.font_size_max as u32).max(min);
+    let wanted = match theme.font_sizing {
+        FontSizing::Fixed => theme.font_size as u32,
+        FontSizing::Fit => fitting.clamp(min, max),
+        FontSizing::Fraction => {
+            ((cell_height as f64 * theme.font_size_fraction as f64).floor() as u32).clamp(min, max)
+        }
+    };
+    wanted.min(fitting)
+}
@@ -0,0 +1,222 @@
+//! Discovery of clickable and focusable UI elements through the AT-SPI accessibility tree.
+//!
+//! The hint mode places a token on each element found here instead of laying out a
+//! uniform grid. Elements are read from the accessibility bus, whose address is taken
+//! from `AT_SPI_BUS_ADDRESS` when set (as at-spi itself does), so a private bus holding
+//! only a test application can be queried the same way as the desktop one.
+
+use std::env;
+use std::time::Duration;
+use zbus::blocking::Connection;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+const REGISTRY_NAME: &str = "org.a11y.atspi.Registry";
+const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+const ACCESSIBLE_IFACE: &str = "org.a11y.atspi.Accessible";
+const COMPONENT_IFACE: &str = "org.a11y.atspi.Component";
+const ACTION_IFACE: &str = "org.a11y.atspi.Action";
+
+// Indices into the AT-SPI state set, see `AtspiStateType`.
+const STATE_ACTIVE: u32 = 1;
+const STATE_ENABLED: u32 = 8;
+const STATE_FOCUSABLE: u32 = 11;
+const STATE_SENSITIVE: u32 = 24;
+const STATE_SHOWING: u32 = 25;
+const STATE_VISIBLE: u32 = 30;
+
+// `ATSPI_COORD_TYPE_SCREEN`
+const COORD_TYPE_SCREEN: u32 = 0;
+
+/// Upper bound on visited accessibles, so huge trees (e.g. long lists) can't stall the overlay.
+const MAX_VISITED: usize = 5000;
+
+/// An on-screen element that can be selected in hint mode.
+/// The rectangle is in screen coordinates as reported by the toolkit.
+#[derive(Debug, Clone)]
+pub struct HintTarget {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub role: String,
+    pub name: String,
+}
+
+impl HintTarget {
+    /// The center of the element, where the pointer is moved to.
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+}
+
+/// A reference to a remote accessible object: its bus name and object path.
+type AccessibleRef = (String, OwnedObjectPath);
+
+/// Returns the address of the accessibility bus.
+/// `AT_SPI_BUS_ADDRESS` takes precedence over asking `org.a11y.Bus` on the session bus.
+pub fn a11y_bus_address() -> zbus::Result<String> {
+    if let Ok(address) = env::var("AT_SPI_BUS_ADDRESS")
+        && !address.is_empty()
+    {
+        return Ok(address);
+    }
+    let session = Connection::session()?;
+    let reply = session.call_method(
+        Some("org.a11y.Bus"),
+        "/org/a11y/bus",
+        Some("org.a11y.Bus"),
+        "GetAddress",
+        &(),
+    )?;
+    reply.body().deserialize::<String>()
+}
+
+/// How long an application gets to answer a call before it is skipped as hung.
+const CALL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connects to the accessibility bus at `address`.
+pub fn connect(address: &str) -> zbus::Result<Connection> {
+    zbus::blocking::connection::Builder::address(address)?
+        .method_timeout(CALL_TIMEOUT)
+        .build()
+}
+
+/// Collects the selectable elements of one application window.
+///
+/// When `app_name` is `None` the window holding the active state is used, which is the
+/// focused window. Passing an application name instead picks the first showing window of
+/// that application, which is what tests against a private bus rely on since nothing is
+/// focused there. Elements are kept when they are showing, visible, enabled and either
+/// expose the Action interface or are focusable. Applications and elements failing to
+/// answer, e.g. defunct or hung ones, are skipped rather than failing the whole walk.
+pub fn collect_targets(conn: &Connection, app_name: Option<&str>) -> zbus::Result<Vec<HintTarget>> {
+    let root: AccessibleRef = (REGISTRY_NAME.to_string(), OwnedObjectPath::try_from(ROOT_PATH)?);
+    let mut window = None;
+    for app in children(conn, &root)? {
+        if let Some(wanted) = app_name
+            && accessible_name(conn, &app).unwrap_or_default() != wanted
+        {
+            continue;
+        }
+        let Ok(candidates) = children(conn, &app) else {
+            continue;
+        };
+        for candidate in candidates {
+            let Ok(states) = state_set(conn, &candidate) else {
+                continue;
+            };
+            let found = match app_name {
+                Some(_) => has_state(&states, STATE_SHOWING),
+                None => has_state(&states, STATE_ACTIVE),
+            };
+            if found {
+                window = Some(candidate);
+                break;
+            }
+        }
+        if window.is_some() {
+            break;
+        }
+    }
+
+    let mut targets = Vec::new();
+    let Some(window) = window else {
+        return Ok(targets);
+    };
+
+    // Depth-first walk, skipping subtrees that are not showing.
+    let mut stack = children(conn, &window).unwrap_or_default();
+    let mut visited = 0;
+    while let Some(node) = stack.pop() {
+        visited += 1;
+        if visited > MAX_VISITED {
+            break;
+        }
+        let Ok(states) = state_set(conn, &node) else {
+            continue;
+        };
+        if !has_state(&states, STATE_SHOWING) {
+            continue;
+        }
+        let selectable = has_state(&states, STATE_VISIBLE)
+            && (has_state(&states, STATE_ENABLED) || has_state(&states, STATE_SENSITIVE))
+            && (has_state(&states, STATE_FOCUSABLE)
+                || interfaces(conn, &node).is_ok_and(|ifaces| ifaces.iter().any(|i| i == ACTION_IFACE)));
+        if selectable
+            && let Ok((x, y, width, height)) = extents(conn, &node)
+            && width > 0
+            && height > 0
+        {
+            targets.push(HintTarget {
+                x,
+                y,
+                width,
+                height,
+                role: call::<String>(conn, &node, ACCESSIBLE_IFACE, "GetRoleName").unwrap_or_default(),
+                name: accessible_name(conn, &node).unwrap_or_default(),
+            });
+        }
+        let Ok(mut kids) = children(conn, &node) else {
+            continue;
+        };
+        // Reverse so siblings are visited, and therefore labelled, in reading order.
+        kids.reverse();
+        stack.extend(kids);
+    }
+    Ok(targets)
+}
+
+fn call<T>(conn: &Connection, node: &AccessibleRef, iface: &str, method: &str) -> zbus::Result<T>
+where
+    T: for<'d> serde::Deserialize<'d> + zbus::zvariant::Type,
+{
+    let reply = conn.call_method(
+        Some(node.0.as_str()),
+        node.1.as_str(),
+        Some(iface),
+        method,
+        &(),
+    )?;
+    reply.body().deserialize::<T>()
+}
+
+fn children(conn: &Connection, node: &AccessibleRef) -> zbus::Result<Vec<AccessibleRef>> {
+    call(conn, node, ACCESSIBLE_IFACE, "GetChildren")
+}
+
+fn state_set(conn: &Connection, node: &AccessibleRef) -> zbus::Result<Vec<u32>> {
+    call(conn, node, ACCESSIBLE_IFACE, "GetState")
+}
+
+fn interfaces(conn: &Connection, node: &AccessibleRef) -> zbus::Result<Vec<String>> {
+    call(conn, node, ACCESSIBLE_IFACE, "GetInterfaces")
+}
+
+fn extents(conn: &Connection, node: &AccessibleRef) -> zbus::Result<(i32, i32, i32, i32)> {
+    let reply = conn.call_method(
+        Some(node.0.as_str()),
+        node.1.as_str(),
+        Some(COMPONENT_IFACE),
+        "GetExtents",
+        &(COORD_TYPE_SCREEN,),
+    )?;
+    reply.body().deserialize::<(i32, i32, i32, i32)>()
+}
+
+fn accessible_name(conn: &Connection, node: &AccessibleRef) -> zbus::Result<String> {
+    let reply = conn.call_method(
+        Some(node.0.as_str()),
+        node.1.as_str(),
+        Some("org.freedesktop.DBus.Properties"),
+        "Get",
+        &(ACCESSIBLE_IFACE, "Name"),
+    )?;
+    let value = reply.body().deserialize::<OwnedValue>()?;
+    Ok(String::try_from(value).unwrap_or_default())
+}
+
+fn has_state(states: &[u32], state: u32) -> bool {
+    states
+        .get((state / 32) as usize)
+        .is_some_and(|word| word & (1 << (state % 32)) != 0)
+}
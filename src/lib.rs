@@ -3,11 +3,24 @@
 use serde::{Serialize, Deserialize};
 
 pub mod conf;
+pub mod conf_doc;
+pub mod daemon;
+pub mod focus;
+pub mod geometry;
+pub mod hint;
+pub mod logging;
+pub mod pointer;
+pub mod protocol;
+pub mod schema;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SharedData {
     pub config: conf::Conf,
     pub tokens: Vec<String>,
+    /// Index of the cell selected by the previous client, kept by the server.
+    pub last_selection: Option<usize>,
+    /// The profile `config` was resolved for, if any.
+    pub profile: Option<String>,
 }
 
 /// ErgonomicKeys holds two lists of keys:
@@ -53,6 +66,12 @@ impl SelectedKeys {
     }
 }
 
+/// The number of tokens `generate_token_list` can make from `keys`.
+pub fn token_capacity(keys: &SelectedKeys) -> usize {
+    let (left, right) = (keys.left.len(), keys.right.len());
+    2 * left * right + left * left.saturating_sub(1) + right * right.saturating_sub(1)
+}
+
 /// Generate a list of tokens to fill a grid based on ergonomic key combinations.
 ///
 /// It works in four phases:
@@ -104,3 +123,40 @@ pub fn generate_token_list(total: usize, keys: &SelectedKeys) -> Vec<String> {
     tokens.truncate(total);
     tokens
 }
+
+/// Generate a list of tokens where the label encodes the cell position.
+///
+/// The first letter of each token is taken from `left` by row index and the
+/// second letter from `right` by column index, so cells in the same row share
+/// their first letter and cells in the same column share their second letter.
+/// Tokens are returned row-major, matching the order used by the grid view.
+/// Panics if there are fewer left keys than rows or fewer right keys than columns.
+pub fn generate_coordinate_token_list(rows: usize, columns: usize, keys: &SelectedKeys) -> Vec<String> {
+    if keys.left.len() < rows || keys.right.len() < columns {
+        panic!(
+            "Grid too large: {} rows x {} columns need at least {} left keys and {} right keys.",
+            rows, columns, rows, columns
+        );
+    }
+    let mut tokens: Vec<String> = Vec::with_capacity(rows * columns);
+    for r in &keys.left[..rows] {
+        for c in &keys.right[..columns] {
+            tokens.push(format!("{}{}", r, c));
+        }
+    }
+    tokens
+}
+
+/// Generate the tokens for every cell of a grid according to its label mode.
+/// Coordinates only make sense for a single grid, so a grid split into regions is
+/// always labelled with tokens, assigned across the regions in order.
+pub fn generate_grid_tokens(grid: &conf::ConfGrid) -> Vec<String> {
+    let selected_keys = SelectedKeys::new(&grid.key_left, &grid.key_right);
+    match grid.label_mode {
+        conf::LabelMode::Coordinates if grid.regions.is_empty() => {
+            let region = &grid.effective_regions()[0];
+            generate_coordinate_token_list(region.row_count(), region.column_count(), &selected_keys)
+        }
+        _ => generate_token_list(grid.cell_count(), &selected_keys),
+    }
+}
@@ -0,0 +1,237 @@
+//! Logging shared by the server and the client.
+//!
+//! Records go to stderr and, when `[log] file` is set, to `<log dir>/<app name>.log`,
+//! which is rotated once it would grow past `max_size`: the file becomes `.1`, `.1`
+//! becomes `.2`, and so on up to `max_files`. Key-value pairs given to the `log` macros,
+//! as in `info!(x = 10, y = 20; "Moving the pointer")`, are kept as fields: `key=value`
+//! after the message in text, a `fields` object in JSON.
+
+use crate::conf::{ConfLog, LOG_DIR_DEFAULT, expand_path};
+use log::kv::{self, VisitSource};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::{Map, Value};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+struct Logger {
+    state: Mutex<LoggerState>,
+}
+
+struct LoggerState {
+    json: bool,
+    file: Option<RotatingFile>,
+}
+
+static LOGGER: Logger = Logger {
+    state: Mutex::new(LoggerState { json: false, file: None }),
+};
+
+/// Sets up logging for `app_name` from `conf`, with `level` taking precedence over the
+/// configured level. May be called again once the configuration is known, which the
+/// client does after asking the server for it.
+pub fn init(app_name: &str, conf: &ConfLog, level: Option<LevelFilter>) {
+    let _ = log::set_logger(&LOGGER);
+    let configured = conf.level.parse::<LevelFilter>();
+    log::set_max_level(level.or(configured.as_ref().ok().copied()).unwrap_or(LevelFilter::Info));
+
+    let file = if conf.file {
+        let dir = if conf.dir.is_empty() {
+            LOG_DIR_DEFAULT.get().cloned().unwrap_or_else(std::env::temp_dir)
+        } else {
+            expand_path(&conf.dir)
+        };
+        let path = dir.join(format!("{}.log", app_name));
+        match RotatingFile::open(&path, conf.max_size, conf.max_files) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Failed to open the log file {:?}: {}", path, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    {
+        let mut state = LOGGER.state.lock().unwrap();
+        state.json = conf.json;
+        state.file = file;
+    }
+    if configured.is_err() {
+        log::warn!("Unknown log level {:?}, using info.", conf.level);
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut fields = Fields(Vec::new());
+        let _ = record.key_values().visit(&mut fields);
+        let mut state = self.state.lock().unwrap();
+        let line = if state.json {
+            json_line(record, &fields.0)
+        } else {
+            text_line(record, &fields.0)
+        };
+        let _ = io::stderr().write_all(line.as_bytes());
+        if let Some(file) = &mut state.file
+            && let Err(e) = file.write_line(&line)
+        {
+            eprintln!("Failed to write to the log file: {}", e);
+            state.file = None;
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// The key-value pairs of a record, in the order given.
+struct Fields(Vec<(String, Value)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(b) = value.to_bool() {
+            Value::from(b)
+        } else if let Some(n) = value.to_i64() {
+            Value::from(n)
+        } else if let Some(n) = value.to_u64() {
+            Value::from(n)
+        } else if let Some(n) = value.to_f64() {
+            Value::from(n)
+        } else {
+            Value::from(value.to_string())
+        };
+        self.0.push((key.to_string(), value));
+        Ok(())
+    }
+}
+
+fn text_line(record: &Record, fields: &[(String, Value)]) -> String {
+    let mut line = format!(
+        "{} {:<5} {}: {}",
+        timestamp(SystemTime::now()),
+        record.level(),
+        record.target(),
+        record.args()
+    );
+    for (key, value) in fields {
+        match value {
+            // Quoted only when needed to tell where the value ends.
+            Value::String(s) if s.is_empty() || s.contains(|c: char| c.is_whitespace() || "\"=".contains(c)) => {
+                line.push_str(&format!(" {}={:?}", key, s))
+            }
+            Value::String(s) => line.push_str(&format!(" {}={}", key, s)),
+            value => line.push_str(&format!(" {}={}", key, value)),
+        }
+    }
+    line.push('\n');
+    line
+}
+
+fn json_line(record: &Record, fields: &[(String, Value)]) -> String {
+    let mut object = Map::new();
+    object.insert("time".into(), timestamp(SystemTime::now()).into());
+    object.insert("level".into(), record.level().as_str().into());
+    object.insert("target".into(), record.target().into());
+    object.insert("message".into(), record.args().to_string().into());
+    if !fields.is_empty() {
+        object.insert("fields".into(), Value::Object(fields.iter().cloned().collect()));
+    }
+    let mut line = Value::Object(object).to_string();
+    line.push('\n');
+    line
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp with milliseconds.
+pub fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    let seconds_of_day = seconds % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// The proleptic Gregorian date `days` after 1970-01-01, after Howard Hinnant's
+/// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// A log file rotated by size, keeping `max_files` older files next to it.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: u32,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Opens `path` for appending, creating it and its directory when needed.
+    pub fn open(path: &Path, max_size: u64, max_files: u32) -> io::Result<RotatingFile> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile { path: path.to_path_buf(), max_size, max_files, file, size })
+    }
+
+    /// Appends `line`, rotating first when it would take the file past `max_size`.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// The path of the `index`th older file.
+    pub fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files > 0 {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
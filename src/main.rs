@@ -9,91 +9,435 @@
 
 extern crate gdk4_sys; // For gdk_keyval_to_unicode
 
-mod conf;
-
 use std::env;
-use std::net::TcpStream;
-use std::io::{Read, BufReader};
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
 use std::char;
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::time::Instant;
 use clap::{Arg, value_parser, Command as ClapCommand};
-use crate::conf::{
+use gtk_cursor_navigator::conf::{
     APP_NAME, CONF_DIR_DEFAULT, CONF_FILE_SUFFIX, STYLE_FILE_SUFFIX, LOG_DIR_DEFAULT,
+    expand_path, user_config_dir,
 };
 // use gio::prelude::*;
 use gtk4::{
-    prelude::{WidgetExt, GtkWindowExt, GridExt,
-    ApplicationExtManual, ApplicationExt},
-    Application, ApplicationWindow, CssProvider, EventControllerKey, Grid, Label,
-    STYLE_PROVIDER_PRIORITY_APPLICATION,
+    prelude::{Cast, IsA, WidgetExt, GtkWindowExt, BoxExt, FixedExt, NativeExt,
+    DrawingAreaExt, DrawingAreaExtManual, EventControllerExt, ApplicationExtManual, ApplicationExt},
+    gdk::{self, prelude::{DisplayExt, MonitorExt, SurfaceExt}},
+    gio::prelude::ListModelExtManual,
+    Application, ApplicationWindow, CssProvider, DrawingArea, EventControllerKey,
+    EventControllerMotion, Fixed, Label,
+    Orientation, PropagationPhase,
+    STYLE_PROVIDER_PRIORITY_APPLICATION, STYLE_PROVIDER_PRIORITY_USER,
 };
 use gtk4_layer_shell::{Edge, Layer, KeyboardMode, LayerShell};
-use log::debug;
+use log::{debug, error, info, warn, LevelFilter};
 use glib::{
     translate::IntoGlib,
     Propagation,
 };
-use gtk_cursor_navigator::SharedData;  // Provided by your lib.rs
-
-/// Connects to the server via TCP (using BufReader) and retrieves the shared data.
-fn retrieve_shared_data_from_server(server_addr: &str) -> SharedData {
-    let stream = TcpStream::connect(server_addr)
-        .expect("Failed to connect to server");
-    let mut reader = BufReader::new(stream);
-    let mut buffer = String::new();
-    reader
-        .read_to_string(&mut buffer)
-        .expect("Failed to read from server");
+use gtk_cursor_navigator::{SharedData, SelectedKeys, generate_token_list, token_capacity};  // Provided by your lib.rs
+use gtk_cursor_navigator::focus::{self, FocusedWindow};
+use gtk_cursor_navigator::hint::{self, HintTarget};
+use gtk_cursor_navigator::logging;
+use gtk_cursor_navigator::protocol::{send_request, Request};
+use gtk_cursor_navigator::conf::{Conf, ConfLog, ConfTheme, LabelMode, LineStyle, Renderer};
+use gtk_cursor_navigator::geometry::{
+    label_font_size, line_start, parse_region, region_on_output, track_offsets, OverlayGeometry,
+    Rect,
+    LABEL_PILL_PADDING,
+};
+use gtk_cursor_navigator::pointer::{self, PointerBackend, ScrollAmount, ScrollAxis};
+
+/// Connects to the server via TCP and retrieves the shared data, for the profile named
+/// `profile` or else the one matching the focused `window`.
+fn retrieve_shared_data_from_server(
+    server_addr: &str,
+    profile: Option<String>,
+    window: Option<FocusedWindow>,
+) -> SharedData {
+    let buffer = send_request(server_addr, &Request::SharedData { profile, window })
+        .expect("Failed to retrieve shared data from server");
     debug!("Received raw data: {}", buffer);
     serde_json::from_str(&buffer)
         .expect("Failed to deserialize JSON from server")
 }
 
+/// Tells the server which cell was selected, so the next overlay can offer it again.
+/// Failing to do so only loses that history, so errors are reported and ignored.
+fn record_selection_on_server(server_addr: &str, index: usize, profile: Option<String>) {
+    if let Err(e) = send_request(server_addr, &Request::RecordSelection { index, profile }) {
+        warn!("Failed to record the selection on the server: {}", e);
+    }
+}
+
 /// Generates a CSS string from the theme in the configuration.
 /// Note: min-width and min-height are fixed to "0px" per your requirements.
-fn generate_css_from_theme(theme: &gtk_cursor_navigator::conf::ConfTheme) -> String {
+///
+/// Colors are combined with their opacity using the `alpha()` CSS function, so the cell
+/// background can be see-through while text and lines stay crisp. With `transparent_cells`
+/// the cells have no background and the labels sit on `.label-pill` backgrounds instead.
+///
+/// These rules use the stable style names a user stylesheet can override:
+/// - `window.gcn-overlay`: the overlay window; `.hint-overlay` / `.adjust-overlay` in those modes.
+/// - `.cell-grid`: the layer holding the cells; `.grid-lines`: the grid lines drawn over it,
+///   styled by the `line_*` and `subgrid*` theme settings rather than CSS.
+/// - `.label-cell`: a grid cell, and `.label-hint`: a hint label; both hold a `.label-pill`
+///   box with a `.label-prefix` label showing the typed part of the token and a `.label-rest`
+///   label with the remainder.
+/// - `.matched` / `.dimmed`: cells whose token does / doesn't start with the typed input.
+/// - `.last-cell` / `.pointer-cell`: the previously selected cell and the cell under the pointer.
+/// - `.label-header`: row and column headers in coordinates label mode.
+fn generate_css_from_theme(theme: &ConfTheme) -> String {
+    let background = format!("alpha({}, {})", theme.background_color, theme.background_opacity);
+    let (cell_background, pill_background) = if theme.transparent_cells {
+        ("transparent".to_string(), background.clone())
+    } else {
+        (background.clone(), "transparent".to_string())
+    };
     format!(
-        ".label-cell {{
-            background-color: {};
-            color: {};
-            border: {}px solid {};
+        "window.gcn-overlay, window.hint-overlay, window.adjust-overlay {{
+            background-color: transparent;
+        }}
+        .label-cell {{
+            background-color: {cell_background};
+            color: {foreground};
+            padding: 0px;
+            font-weight: {font_weight};
+            font-size: {font_size}px;
+            min-width: 0px;
+            min-height: 0px;
+        }}
+        .label-pill {{
+            background-color: {pill_background};
+            border-radius: 4px;
+            padding: 0px {pill_padding}px;
+        }}
+        .label-prefix {{
+            color: {highlight};
+        }}
+        .matched {{
+            color: {foreground};
+        }}
+        .dimmed {{
+            opacity: {dimmed_opacity};
+        }}
+        .label-cell.last-cell {{
+            color: {line};
+        }}
+        .label-cell.pointer-cell {{
+            background-color: {line};
+            color: {background};
+        }}
+        .label-hint {{
+            background-color: {background};
+            color: {foreground};
+            border: {line_pixel}px solid {line};
+            padding: 0px 2px;
+            font-weight: {font_weight};
+            font-size: {font_size}px;
+        }}
+        .label-header {{
+            background-color: {line};
+            color: {background};
             padding: 0px;
-            font-weight: {};
-            font-size: {}px;
+            font-weight: {font_weight};
+            font-size: {font_size}px;
             min-width: 0px;
             min-height: 0px;
         }}",
-        theme.background_color,
-        theme.foreground_color,
-        theme.line_pixel,
-        theme.line_color,
-        theme.font_weight,
-        theme.font_size,
+        cell_background = cell_background,
+        pill_background = pill_background,
+        pill_padding = LABEL_PILL_PADDING,
+        background = background,
+        foreground = format!("alpha({}, {})", theme.foreground_color, theme.foreground_opacity),
+        line = format!("alpha({}, {})", theme.line_color, theme.line_opacity),
+        line_pixel = theme.line_pixel,
+        highlight = format!("alpha({}, {})", theme.highlight_color, theme.foreground_opacity),
+        dimmed_opacity = theme.dimmed_opacity,
+        font_weight = theme.font_weight,
+        font_size = theme.font_size,
     )
 }
 
-/// The GTK activation function builds the layer‑shell window with a grid view.
-/// Each cell displays its token and its Label widget is saved for later use in determining
-/// its on‑screen coordinates. Two key controllers are installed: one for exiting the app
-/// (using a configured shortcut) and one for handling token input. When a complete token is
-/// typed, the target cell’s Label widget is queried for its position using
-/// `translate_coordinates()`. Its center is determined and (after adding a correction factor for
-/// your reserved left margin) the coordinates are sent to ydotool as absolute pixel values
-/// using the command:
-///     ydotool mousemove --absolute -x <X> -y <Y>
-fn activate(application: &gtk4::Application, shared_data: SharedData, css_data: String) {
+/// Loads the user stylesheet on top of the CSS generated from the theme.
+/// It is installed with the user priority, so its rules win over the generated ones.
+fn load_user_stylesheet(path: &Path) {
+    if !path.exists() {
+        debug!("No user stylesheet at {:?}", path);
+        return;
+    }
+    let provider = CssProvider::new();
+    provider.connect_parsing_error(|_provider, section, error| {
+        warn!("User stylesheet error at {}: {}", section, error);
+    });
+    provider.load_from_path(path);
+    if let Some(display) = gtk4::gdk::Display::default() {
+        gtk4::StyleContext::add_provider_for_display(
+            &display,
+            &provider,
+            STYLE_PROVIDER_PRIORITY_USER,
+        );
+    }
+}
+
+/// Keeps the label font size fitted to the cells, following `theme.font_sizing`.
+/// The font size is set through its own style provider, above the theme but below
+/// the user stylesheet.
+struct FontFitter {
+    provider: CssProvider,
+    theme: ConfTheme,
+    chars: usize,
+    current_size: Cell<u32>,
+}
+
+impl FontFitter {
+    fn new(theme: &ConfTheme, chars: usize) -> Self {
+        let provider = CssProvider::new();
+        if let Some(display) = gtk4::gdk::Display::default() {
+            gtk4::StyleContext::add_provider_for_display(
+                &display,
+                &provider,
+                STYLE_PROVIDER_PRIORITY_APPLICATION + 1,
+            );
+        }
+        FontFitter { provider, theme: theme.clone(), chars, current_size: Cell::new(0) }
+    }
+
+    /// Fits the labels to cells of at least `cell_width` × `cell_height`. Returns whether
+    /// the labels fit at all; when not even a 1 pixel font does, they are to be hidden.
+    fn fit(&self, cell_width: u32, cell_height: u32) -> bool {
+        let font_size = label_font_size(cell_width, cell_height, self.chars, &self.theme);
+        if font_size == 0 {
+            debug!("Cells are {}x{}, too small for labels", cell_width, cell_height);
+            return false;
+        }
+        // Reloading restyles and lays out the overlay again, so only do it on changes.
+        if font_size != self.current_size.get() {
+            self.current_size.set(font_size);
+            debug!("Cells are {}x{}, using font size {}px", cell_width, cell_height, font_size);
+            self.provider.load_from_data(&format!(".label-cell {{ font-size: {}px; }}", font_size));
+        }
+        true
+    }
+}
+
+/// Sets the cairo source color from a theme color and an opacity.
+fn set_source_color(cr: &gtk4::cairo::Context, color: &str, opacity: f32) {
+    let rgba = gdk::RGBA::parse(color).unwrap_or(gdk::RGBA::WHITE);
+    cr.set_source_rgba(
+        rgba.red() as f64,
+        rgba.green() as f64,
+        rgba.blue() as f64,
+        (rgba.alpha() * opacity) as f64,
+    );
+}
+
+/// Draws the lines of a laid out grid, one line per boundary of each region, at the
+/// boundaries given by the geometry model used for the cells. Lines are drawn in device
+/// pixels, like the layout, so they stay crisp on scaled outputs. Outer lines are kept
+/// inside the overlay so they are as thick as the inner ones. When `theme.subgrid` is
+/// above one, dotted guides split every cell into that many parts beforehand.
+fn draw_grid_lines(cr: &gtk4::cairo::Context, geometry: &OverlayGeometry, theme: &ConfTheme) {
+    let layout = &geometry.layout;
+    let (width, height) = (layout.width, layout.height);
+    let scale = geometry.scale;
+    let _ = cr.save();
+    cr.scale(1.0 / scale, 1.0 / scale);
+
+    let subgrid = theme.subgrid as u32;
+    if subgrid > 1 {
+        set_source_color(cr, &theme.subgrid_color, theme.subgrid_opacity);
+        cr.set_line_width(1.0);
+        cr.set_dash(&[1.0, 3.0], 0.0);
+        for region in &layout.regions {
+            let bounds = region.bounds();
+            for track in region.xs.windows(2) {
+                for offset in &track_offsets(track[1] - track[0], subgrid)[1..subgrid as usize] {
+                    let x = (track[0] + offset) as f64 + 0.5;
+                    cr.move_to(x, bounds.y);
+                    cr.line_to(x, bounds.y + bounds.height);
+                }
+            }
+            for track in region.ys.windows(2) {
+                for offset in &track_offsets(track[1] - track[0], subgrid)[1..subgrid as usize] {
+                    let y = (track[0] + offset) as f64 + 0.5;
+                    cr.move_to(bounds.x, y);
+                    cr.line_to(bounds.x + bounds.width, y);
+                }
+            }
+        }
+        let _ = cr.stroke();
+    }
+
+    let thickness = (theme.line_pixel as f64 * scale).round() as u32;
+    if thickness == 0 {
+        let _ = cr.restore();
+        return;
+    }
+    set_source_color(cr, &theme.line_color, theme.line_opacity);
+    cr.set_line_width(thickness as f64);
+    let dash = theme.line_dash.max(1) as f64 * scale;
+    match theme.line_style {
+        LineStyle::Solid => cr.set_dash(&[], 0.0),
+        LineStyle::Dashed => cr.set_dash(&[dash, dash], 0.0),
+        LineStyle::Dotted => cr.set_dash(&[thickness as f64, thickness as f64 * 2.0], 0.0),
+    }
+    let half = thickness as f64 / 2.0;
+    for region in &layout.regions {
+        let bounds = region.bounds();
+        for &x in &region.xs {
+            let x = line_start(x, thickness, width) as f64 + half;
+            cr.move_to(x, bounds.y);
+            cr.line_to(x, bounds.y + bounds.height);
+        }
+        for &y in &region.ys {
+            let y = line_start(y, thickness, height) as f64 + half;
+            cr.move_to(bounds.x, y);
+            cr.line_to(bounds.x + bounds.width, y);
+        }
+    }
+    let _ = cr.stroke();
+    let _ = cr.restore();
+}
+
+/// A token shown on the overlay. The part of the token typed so far is shown in its own
+/// `.label-prefix` label, so it can be styled separately from the `.label-rest` label.
+/// Both sit centered in a `.label-pill` box, which can back the text on its own when the
+/// cell itself is transparent.
+#[derive(Clone)]
+struct TokenLabel {
+    container: gtk4::Box,
+    pill: gtk4::Box,
+    prefix: Label,
+    rest: Label,
+    token: String,
+}
+
+impl TokenLabel {
+    fn new(token: &str, css_class: &str) -> Self {
+        let container = gtk4::Box::new(Orientation::Horizontal, 0);
+        container.add_css_class(css_class);
+        container.set_overflow(gtk4::Overflow::Hidden);
+        let pill = gtk4::Box::new(Orientation::Horizontal, 0);
+        pill.add_css_class("label-pill");
+        pill.set_hexpand(true);
+        pill.set_halign(gtk4::Align::Center);
+        pill.set_valign(gtk4::Align::Center);
+        let prefix = Label::new(None);
+        prefix.add_css_class("label-prefix");
+        let rest = Label::new(Some(token));
+        rest.add_css_class("label-rest");
+        pill.append(&prefix);
+        pill.append(&rest);
+        container.append(&pill);
+        TokenLabel { container, pill, prefix, rest, token: token.to_string() }
+    }
+
+    /// Reflects the typed `input`: a matching token shows the input as its prefix and
+    /// gets the `.matched` class, a token not matching gets `.dimmed`.
+    fn show_input(&self, input: &str) {
+        let matched = !input.is_empty() && self.token.starts_with(input);
+        let (prefix, rest) = if matched {
+            self.token.split_at(input.len())
+        } else {
+            ("", self.token.as_str())
+        };
+        self.prefix.set_text(prefix);
+        self.rest.set_text(rest);
+        if matched {
+            self.container.add_css_class("matched");
+        } else {
+            self.container.remove_css_class("matched");
+        }
+        if !input.is_empty() && !matched {
+            self.container.add_css_class("dimmed");
+        } else {
+            self.container.remove_css_class("dimmed");
+        }
+    }
+}
+
+/// The size of the header strips, from the font size.
+fn header_strip(config: &Conf) -> i32 {
+    config.theme.font_size as i32 * 2
+}
+
+/// Surrounds the grid with a row header strip on the left and a column header strip
+/// on top, showing which key selects each row and column in coordinates label mode.
+/// The strips are sized from the font size so the cells keep as much room as possible.
+fn wrap_grid_with_headers(grid: &impl IsA<gtk4::Widget>, config: &Conf) -> gtk4::Box {
+    let keys = SelectedKeys::new(&config.grid.key_left, &config.grid.key_right);
+    let region = &config.grid.effective_regions()[0];
+    let strip = header_strip(config);
+
+    let column_headers = gtk4::Box::new(Orientation::Horizontal, 0);
+    column_headers.set_homogeneous(true);
+    column_headers.set_hexpand(true);
+    for key in keys.right.iter().take(region.column_count()) {
+        let header = Label::new(Some(key));
+        header.add_css_class("label-header");
+        header.set_size_request(-1, strip);
+        column_headers.append(&header);
+    }
+    let row_headers = gtk4::Box::new(Orientation::Vertical, 0);
+    row_headers.set_homogeneous(true);
+    row_headers.set_vexpand(true);
+    for key in keys.left.iter().take(region.row_count()) {
+        let header = Label::new(Some(key));
+        header.add_css_class("label-header");
+        header.set_size_request(strip, -1);
+        row_headers.append(&header);
+    }
+
+    // The corner keeps the column headers aligned with the grid columns.
+    let corner = Label::new(None);
+    corner.add_css_class("label-header");
+    corner.set_size_request(strip, strip);
+    let top = gtk4::Box::new(Orientation::Horizontal, 0);
+    top.append(&corner);
+    top.append(&column_headers);
+
+    let body = gtk4::Box::new(Orientation::Horizontal, 0);
+    body.append(&row_headers);
+    body.append(grid);
+
+    let container = gtk4::Box::new(Orientation::Vertical, 0);
+    container.append(&top);
+    container.append(&body);
+    container
+}
+
+/// Builds the borderless layer‑shell window shared by the grid and hint views.
+/// It covers the whole output on the overlay layer, takes the keyboard exclusively,
+/// and installs the CSS generated from the theme for the display.
+fn build_overlay_window(
+    application: &gtk4::Application,
+    config: &gtk_cursor_navigator::conf::Conf,
+    css_data: &str,
+    scope: Option<Rect>,
+) -> ApplicationWindow {
     let window = ApplicationWindow::new(application);
-    let config = &shared_data.config;
 
     window.set_decorated(false);
     window.set_title(Some("Layer Shell Grid Overlay"));
-    // window.set_opacity(config.theme.foreground_opacity as f64);
+    window.add_css_class("gcn-overlay");
     window.set_opacity(config.theme.opacity as f64);
 
-    // if config.grid.cover_screen {
+    if let Some(rect) = scope {
+        // Only the top-left corner is anchored, so the margins place the overlay over the
+        // rectangle. Margins count from the area left by the reserved bars.
+        let left = rect.x as i32 - config.reserved.left as i32;
+        let top = rect.y as i32 - config.reserved.top as i32;
+        window.set_default_size(rect.width as i32, rect.height as i32);
+        window.connect_realize(move |win| {
+            win.set_anchor(Edge::Top, true);
+            win.set_anchor(Edge::Left, true);
+            win.set_margin(Edge::Top, top.max(0));
+            win.set_margin(Edge::Left, left.max(0));
+        });
+    } else {
         window.connect_realize(|win| {
             win.set_anchor(Edge::Top, true);
             win.set_anchor(Edge::Bottom, true);
@@ -104,9 +448,7 @@ fn activate(application: &gtk4::Application, shared_data: SharedData, css_data:
             win.set_margin(Edge::Left, 0);
             win.set_margin(Edge::Right, 0);
         });
-    // } else {
-    //     window.set_default_size(config.grid.width as i32, config.grid.height as i32);
-    // }
+    }
 
     window.init_layer_shell();
     window.set_layer(Layer::Overlay);
@@ -114,7 +456,7 @@ fn activate(application: &gtk4::Application, shared_data: SharedData, css_data:
     window.set_exclusive_zone(0);
 
     let provider = CssProvider::new();
-    provider.load_from_data(css_data.as_str());
+    provider.load_from_data(css_data);
     if let Some(display) = gtk4::gdk::Display::default() {
         // Using the deprecated method as in your original code.
         gtk4::StyleContext::add_provider_for_display(
@@ -123,149 +465,922 @@ fn activate(application: &gtk4::Application, shared_data: SharedData, css_data:
             STYLE_PROVIDER_PRIORITY_APPLICATION,
         );
     }
+    window
+}
 
-    // Create and store each cell's Label in a vector.
-    let cell_labels: Rc<RefCell<Vec<Label>>> = Rc::new(RefCell::new(Vec::new()));
-    let grid = Grid::new();
-    grid.set_focusable(true);
-    grid.set_row_homogeneous(true);
-    grid.set_column_homogeneous(true);
-    grid.set_hexpand(true);
-    grid.set_vexpand(true);
-    grid.set_margin_top(0);
-    grid.set_margin_bottom(0);
-    grid.set_margin_start(0);
-    grid.set_margin_end(0);
-
-    let rows = config.grid.rows as i32;
-    let columns = config.grid.columns as i32;
-    for row in 0..rows {
-        for col in 0..columns {
-            let index = (row as usize * (columns as usize)) + (col as usize);
-            let token = if index < shared_data.tokens.len() {
-                &shared_data.tokens[index]
-            } else {
-                ""
-            };
-            let cell_label = Label::new(Some(token));
-            cell_label.add_css_class("label-cell");
-            grid.attach(&cell_label, col, row, 1, 1);
-            cell_labels.borrow_mut().push(cell_label);
-        }
-    }
-    window.set_child(Some(&grid));
-
-    // --- Key Controller for Exit ---
+/// Installs the key controller terminating the program with `exit_code` on the
+/// configured exit shortcut.
+fn install_exit_controller(window: &ApplicationWindow, exit_key: u32, exit_code: i32) {
     let key_controller = EventControllerKey::new();
+    key_controller.connect_key_pressed(move |_controller, keyval, _keycode, _modifiers| {
+        let key_u32: u32 = keyval.into_glib();
+        if key_u32 == exit_key {
+            std::process::exit(exit_code);
+        }
+        Propagation::Proceed
+    });
+    window.add_controller(key_controller);
+}
+
+/// Installs the key controller collecting typed letters into a buffer and matching it
+/// against `tokens`. Once the buffer equals exactly one token, `on_select` is called with
+/// that token's index, and returns whether it acted on the selection. A buffer matching
+/// nothing, or two letters without a match, is reset.
+/// Pressing one of `jump_keys` selects `last_selection` directly, when there is one.
+/// `on_input` is called with the buffer after every change, so the labels can follow it.
+/// The controller stops matching after a selection, so modes entered from `on_select`
+/// can use letter keys of their own, unless `on_select` couldn't act on it and the overlay
+/// stays as it was.
+fn install_token_controller<I, F>(
+    window: &ApplicationWindow,
+    tokens: Vec<String>,
+    last_selection: Option<usize>,
+    jump_keys: Vec<gdk::Key>,
+    on_input: I,
+    on_select: F,
+) where
+    I: Fn(&str) + 'static,
+    F: Fn(usize) -> bool + 'static,
+{
+    let input_buffer: Rc<RefCell<String>> = Rc::new(RefCell::new(String::new()));
+    let key_controller = EventControllerKey::new();
+    key_controller.connect_key_pressed(move |controller, keyval, _keycode, _modifiers| {
+        if let Some(index) = last_selection.filter(|_| jump_keys.contains(&keyval)) {
+            info!(token = tokens[index].as_str(), index = index; "Targeting the last cell");
+            controller.set_propagation_phase(PropagationPhase::None);
+            if !on_select(index) {
+                controller.set_propagation_phase(PropagationPhase::Bubble);
+            }
+            return Propagation::Stop;
+        }
+        let key_uint: u32 = keyval.into_glib();
+        let unicode = unsafe { gdk4_sys::gdk_keyval_to_unicode(key_uint) };
+        if unicode != 0 {
+            if let Some(ch) = char::from_u32(unicode) {
+                let final_char = if ch.is_alphabetic() { ch.to_ascii_uppercase() } else { ch };
+                input_buffer.borrow_mut().push(final_char);
+            }
+        }
+        let current_input = input_buffer.borrow().clone();
+        if !current_input.is_empty() {
+            let matching: Vec<(usize, String)> = tokens.iter()
+                .enumerate()
+                .filter_map(|(idx, token)| {
+                    if token.starts_with(&current_input) {
+                        Some((idx, token.clone()))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            if matching.is_empty() {
+                input_buffer.borrow_mut().clear();
+            } else if matching.len() == 1 && matching[0].1 == current_input {
+                info!(token = matching[0].1.as_str(), index = matching[0].0; "Targeting a cell");
+                controller.set_propagation_phase(PropagationPhase::None);
+                input_buffer.borrow_mut().clear();
+                if !on_select(matching[0].0) {
+                    controller.set_propagation_phase(PropagationPhase::Bubble);
+                    on_input("");
+                }
+                return Propagation::Stop;
+            } else if current_input.len() >= 2 {
+                input_buffer.borrow_mut().clear();
+            }
+        }
+        on_input(input_buffer.borrow().as_str());
+        Propagation::Proceed
+    });
+    window.add_controller(key_controller);
+}
+
+/// Resolves GDK key names from the configuration, skipping names GDK doesn't know.
+fn keys_from_names(names: &[String]) -> Vec<gdk::Key> {
+    names
+        .iter()
+        .filter_map(|name| {
+            let key = gdk::Key::from_name(name.as_str());
+            if key.is_none() {
+                warn!("Unknown key name in configuration: {:?}", name);
+            }
+            key
+        })
+        .collect()
+}
+
+/// Maps a modifier name from the configuration to its GDK modifier mask.
+fn modifier_from_name(name: &str) -> gdk::ModifierType {
+    match name.to_ascii_lowercase().as_str() {
+        "shift" => gdk::ModifierType::SHIFT_MASK,
+        "control" | "ctrl" => gdk::ModifierType::CONTROL_MASK,
+        "alt" => gdk::ModifierType::ALT_MASK,
+        "super" => gdk::ModifierType::SUPER_MASK,
+        _ => {
+            warn!("Unknown modifier name in configuration: {:?}", name);
+            gdk::ModifierType::SHIFT_MASK
+        }
+    }
+}
+
+/// Turns the overlay into the adjust mode once a target was selected and the pointer moved
+/// to `(x, y)`, in global layout coordinates; the overlay surface starts at `origin`, and
+/// the pointer is kept on `output`. The grid is replaced by a crosshair at the pointer on a
+/// transparent surface that lets pointer input through, so wheel events reach the window
+/// below. Until a finish key is pressed, and depending on which of the nudge and scroll
+/// settings are enabled:
+/// - the nudge keys move the pointer by `step` pixels (`large_step` with the modifier held);
+/// - the scroll keys send wheel events at the pointer, repeating with key auto-repeat.
+fn enter_adjust_mode(
+    window: &ApplicationWindow,
+    config: &Conf,
+    pointer: Rc<dyn PointerBackend>,
+    origin: (i32, i32),
+    output: Rect,
+    x: i32,
+    y: i32,
+) {
+    let position = Rc::new(Cell::new((x, y)));
+    let (offset_x, offset_y) = origin;
+    let color = gdk::RGBA::parse(config.theme.line_color.as_str()).unwrap_or(gdk::RGBA::WHITE);
+
+    let crosshair = DrawingArea::new();
+    crosshair.set_hexpand(true);
+    crosshair.set_vexpand(true);
     {
-        let exit_key = config.shortcut.exit_key;
-        key_controller.connect_key_pressed(move |_controller, keyval, _keycode, _modifiers| {
-            let key_u32: u32 = keyval.into_glib();
-            if key_u32 == exit_key {
-                std::process::exit(0);
+        let position = Rc::clone(&position);
+        crosshair.set_draw_func(move |_area, cr, _width, _height| {
+            let (px, py) = position.get();
+            let cx = (px - offset_x) as f64 + 0.5;
+            let cy = (py - offset_y) as f64 + 0.5;
+            cr.set_source_rgba(
+                color.red() as f64,
+                color.green() as f64,
+                color.blue() as f64,
+                color.alpha() as f64,
+            );
+            cr.set_line_width(1.0);
+            // Four arms with a gap in the middle, so the pixel under the pointer stays visible.
+            for (dx, dy) in [(-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0)] {
+                cr.move_to(cx + dx * 4.0, cy + dy * 4.0);
+                cr.line_to(cx + dx * 16.0, cy + dy * 16.0);
             }
-            Propagation::Proceed
+            let _ = cr.stroke();
         });
     }
+    window.add_css_class("adjust-overlay");
+    window.set_child(Some(&crosshair));
+    if let Some(surface) = window.surface() {
+        // An empty input region lets the pointer reach the windows below the overlay.
+        surface.set_input_region(&gtk4::cairo::Region::create());
+    }
+
+    // Disabled actions get no keys, so they never match.
+    let keys_if = |enabled: bool, names: &[String]| {
+        if enabled { keys_from_names(names) } else { Vec::new() }
+    };
+    let nudge = &config.nudge;
+    let left = keys_if(nudge.enabled, &nudge.left);
+    let down = keys_if(nudge.enabled, &nudge.down);
+    let up = keys_if(nudge.enabled, &nudge.up);
+    let right = keys_if(nudge.enabled, &nudge.right);
+    let large_modifier = modifier_from_name(&nudge.large_modifier);
+    let step = nudge.step as i32;
+    let large_step = nudge.large_step as i32;
+
+    let scroll = &config.scroll;
+    let scroll_up = keys_if(scroll.enabled, &scroll.up);
+    let scroll_down = keys_if(scroll.enabled, &scroll.down);
+    let scroll_left = keys_if(scroll.enabled, &scroll.left);
+    let scroll_right = keys_if(scroll.enabled, &scroll.right);
+    let scroll_amount = if scroll.high_resolution {
+        ScrollAmount::HighResolution
+    } else {
+        ScrollAmount::Discrete
+    };
+    let scroll_step = if scroll.high_resolution { scroll.high_resolution_step } else { scroll.step };
+
+    let finish = keys_from_names(&config.shortcut.finish);
+
+    let key_controller = EventControllerKey::new();
+    key_controller.connect_key_pressed(move |_controller, keyval, _keycode, modifiers| {
+        if finish.contains(&keyval) {
+            std::process::exit(0);
+        }
+        // The modifier may turn letters upper case, so compare lower case key values.
+        let keyval = keyval.to_lower();
+
+        let wheel = if scroll_up.contains(&keyval) {
+            Some((ScrollAxis::Vertical, -scroll_step))
+        } else if scroll_down.contains(&keyval) {
+            Some((ScrollAxis::Vertical, scroll_step))
+        } else if scroll_left.contains(&keyval) {
+            Some((ScrollAxis::Horizontal, -scroll_step))
+        } else if scroll_right.contains(&keyval) {
+            Some((ScrollAxis::Horizontal, scroll_step))
+        } else {
+            None
+        };
+        if let Some((axis, amount)) = wheel {
+            if let Err(e) = pointer.scroll(axis, scroll_amount(amount)) {
+                error!("Failed to scroll: {}", e);
+            }
+            return Propagation::Stop;
+        }
+
+        let step = if modifiers.contains(large_modifier) { large_step } else { step };
+        let (dx, dy) = if left.contains(&keyval) {
+            (-step, 0)
+        } else if down.contains(&keyval) {
+            (0, step)
+        } else if up.contains(&keyval) {
+            (0, -step)
+        } else if right.contains(&keyval) {
+            (step, 0)
+        } else {
+            return Propagation::Proceed;
+        };
+        let (px, py) = position.get();
+        let next = output.clamp_pixel(px + dx, py + dy);
+        position.set(next);
+        if let Err(e) = pointer.move_to(next.0, next.1) {
+            error!("Failed to move the pointer: {}", e);
+        }
+        crosshair.queue_draw();
+        Propagation::Stop
+    });
     window.add_controller(key_controller);
+}
 
-    window.present();
-    window.grab_focus();
+/// What the grid's geometry is computed from, besides the output showing it.
+#[derive(Clone)]
+struct GeometrySource {
+    config: Conf,
+    scope: Option<Rect>,
+    header_strip: f64,
+}
 
-    let reserved_left = config.reserved.left as f64; // correction for left-side bar
-    let reserved_right = config.reserved.right as f64; // correction for right-side bar
-    let reserved_top = config.reserved.top as f64; // correction for top-side bar
-    let reserved_bottom = config.reserved.bottom as f64; // correction for bottom-side bar
+impl GeometrySource {
+    /// Computes the geometry for the output showing `widget`. Until GDK knows the output,
+    /// one exactly fitting the widget's window is assumed.
+    fn compute(&self, widget: &impl IsA<gtk4::Widget>) -> OverlayGeometry {
+        let (output, scale) = match monitor_of(widget) {
+            Some(monitor) => monitor,
+            None => {
+                let reserved = &self.config.reserved;
+                let surface = widget.native().and_then(|native| native.surface());
+                let (width, height) = surface.map_or((0, 0), |s| (s.width(), s.height()));
+                let output = Rect::new(
+                    0.0,
+                    0.0,
+                    (width as u32 + reserved.left + reserved.right) as f64,
+                    (height as u32 + reserved.top + reserved.bottom) as f64,
+                );
+                (output, 1.0)
+            }
+        };
+        OverlayGeometry::new(
+            output,
+            scale,
+            &self.config.reserved,
+            self.scope,
+            &self.config.grid,
+            self.header_strip,
+        )
+    }
+}
 
-    let input_buffer: Rc<RefCell<String>> = Rc::new(RefCell::new(String::new()));
-    let tokens_for_match = shared_data.tokens.clone();
-    let cell_labels_for_move = Rc::clone(&cell_labels);
+/// The output showing `widget` in global layout coordinates, and its scale, once GDK knows
+/// which output that is.
+fn monitor_of(widget: &impl IsA<gtk4::Widget>) -> Option<(Rect, f64)> {
+    let surface = widget.native().and_then(|native| native.surface())?;
+    let monitor = surface.display().monitor_at_surface(&surface)?;
+    Some((monitor_rect(&monitor), monitor.scale_factor() as f64))
+}
 
-    // Clone the window so it can be used within the closure.
-    let win_for_translation = window.clone();
+/// The monitor's output in global layout coordinates.
+fn monitor_rect(monitor: &gdk::Monitor) -> Rect {
+    let area = monitor.geometry();
+    Rect::new(area.x() as f64, area.y() as f64, area.width() as f64, area.height() as f64)
+}
 
-    let key_controller2 = EventControllerKey::new();
+/// Makes a `--region` relative to the output showing it, and gives that output's monitor
+/// to put the overlay on. Without an output containing the region, the grid covers the
+/// whole output.
+fn region_scope(region: Rect) -> (Option<Rect>, Option<gdk::Monitor>) {
+    let monitors: Vec<gdk::Monitor> = gdk::Display::default()
+        .map(|display| display.monitors().iter::<gdk::Monitor>().filter_map(Result::ok).collect())
+        .unwrap_or_default();
+    let outputs: Vec<Rect> = monitors.iter().map(monitor_rect).collect();
+    match region_on_output(region, &outputs) {
+        Some((index, scope)) => (Some(scope), Some(monitors[index].clone())),
+        None => {
+            warn!(region:? = region; "The region lies on no output, covering the whole output.");
+            (None, None)
+        }
+    }
+}
+
+/// Recomputes the geometry when a view got its size, and reports when the compositor gave
+/// the view another size than the model expects, which means the reserved edges are off.
+fn update_geometry(
+    slot: &RefCell<Option<OverlayGeometry>>,
+    source: &GeometrySource,
+    widget: &impl IsA<gtk4::Widget>,
+    width: i32,
+    height: i32,
+) -> OverlayGeometry {
+    let geometry = source.compute(widget);
+    let area = geometry.grid_area;
+    if (area.width - width as f64).abs() > 1.0 || (area.height - height as f64).abs() > 1.0 {
+        warn!(
+            "The grid got {}x{} but {}x{} was expected; check the reserved edges in the configuration.",
+            width, height, area.width, area.height
+        );
+    }
+    *slot.borrow_mut() = Some(geometry.clone());
+    geometry
+}
+
+/// The cells of the grid view as built by one of the renderers.
+struct GridView {
+    /// The widget showing the cells over the geometry's grid area.
+    widget: gtk4::Widget,
+    /// The grid's geometry on the output, computed once the view got its size.
+    geometry: Rc<RefCell<Option<OverlayGeometry>>>,
+    /// Reflects the typed input on the labels.
+    show_input: Box<dyn Fn(&str)>,
+    /// Whether the labels are placed on their cells and sized, so a painted frame shows
+    /// the finished grid.
+    laid_out: Rc<Cell<bool>>,
+}
+
+/// Builds the grid view with one widget per cell. Each cell's label is placed on a `Fixed`
+/// at its cell rectangle, and a drawing area on top draws the grid lines at the same
+/// boundaries. The cell under the pointer and the last selected cell get style classes.
+fn build_widget_view(
+    source: &GeometrySource,
+    tokens: &[String],
+    last_selection: Option<usize>,
+) -> GridView {
+    let config = &source.config;
+    // Create and store each cell's label in a vector.
+    let cell_labels: Rc<RefCell<Vec<TokenLabel>>> = Rc::new(RefCell::new(Vec::new()));
+    let geometry: Rc<RefCell<Option<OverlayGeometry>>> = Rc::new(RefCell::new(None));
+    let cells = Fixed::new();
+    cells.add_css_class("cell-grid");
+    cells.set_hexpand(true);
+    cells.set_vexpand(true);
+
+    for index in 0..config.grid.cell_count() {
+        let token = if index < tokens.len() {
+            &tokens[index]
+        } else {
+            ""
+        };
+        let cell_label = TokenLabel::new(token, "label-cell");
+        cells.put(&cell_label.container, 0.0, 0.0);
+        cell_labels.borrow_mut().push(cell_label);
+    }
+    if let Some(index) = last_selection {
+        cell_labels.borrow()[index].container.add_css_class("last-cell");
+    }
+
+    let lines = DrawingArea::new();
+    lines.add_css_class("grid-lines");
+    lines.set_can_target(false);
     {
-        let buffer_cb = Rc::clone(&input_buffer);
-        key_controller2.connect_key_pressed(move |_controller, keyval, _keycode, _modifiers| {
-            let key_uint: u32 = keyval.into_glib();
-            let unicode = unsafe { gdk4_sys::gdk_keyval_to_unicode(key_uint) };
-            if unicode != 0 {
-                if let Some(ch) = char::from_u32(unicode) {
-                    let final_char = if ch.is_alphabetic() { ch.to_ascii_uppercase() } else { ch };
-                    buffer_cb.borrow_mut().push(final_char);
-                }
+        let geometry = Rc::clone(&geometry);
+        let theme = config.theme.clone();
+        lines.set_draw_func(move |_area, cr, _width, _height| {
+            if let Some(geometry) = geometry.borrow().as_ref() {
+                draw_grid_lines(cr, geometry, &theme);
             }
-            let current_input = buffer_cb.borrow().clone();
-            if !current_input.is_empty() {
-                let matching: Vec<(usize, String)> = tokens_for_match.iter()
-                    .enumerate()
-                    .filter_map(|(idx, token)| {
-                        if token.starts_with(&current_input) {
-                            Some((idx, token.clone()))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                if matching.is_empty() {
-                    buffer_cb.borrow_mut().clear();
-                } else if matching.len() == 1 && matching[0].1 == current_input {
-                    println!("Targeting cell: token {} (cell index {}).", matching[0].1, matching[0].0);
-                    let index = matching[0].0;
-
-                    // Retrieve the corresponding Label widget.
-                    let cell_label = cell_labels_for_move.borrow()[index].clone();
-                    // Use translate_coordinates with floating-point zero offsets.
-                    if let Some((label_x, label_y)) = cell_label.translate_coordinates(&win_for_translation, 0.0, 0.0) {
-                        let alloc = cell_label.allocation();
-                        // Compute the center of the label.
-                        let center_x = label_x + (alloc.width() as f64 / 2.0);
-                        let center_y = label_y + (alloc.height() as f64 / 2.0);
-                        // Apply a horizontal correction based on reserved_left.
-                        let abs_x = center_x + (reserved_left - reserved_right);
-                        let abs_y = center_y - (reserved_bottom - reserved_top);
-                        // Convert to integer pixel coordinates.
-                        let abs_x_int = abs_x.round() as i32;
-                        let abs_y_int = abs_y.round() as i32;
-                        println!("Moving cursor to: x={} y={}", abs_x_int, abs_y_int);
-
-                        // Use ydotool to move the mouse pointer.
-                        let status = Command::new("ydotool")
-                            .arg("mousemove")
-                            .arg("--absolute")
-                            .arg("-x")
-                            .arg(format!("{}", abs_x_int))
-                            .arg("-y")
-                            .arg(format!("{}", abs_y_int))
-                            .status()
-                            .expect("Failed to execute ydotool");
-
-                        if status.success() {
-                            println!("Pointer moved successfully.");
-                        } else {
-                            println!("ydotool failed with exit code: {:?}", status);
-                        }
-                        std::process::exit(0);
-                    } else {
-                        println!("Failed to translate cell label coordinates relative to window.");
-                    }
-                } else if current_input.len() >= 2 {
-                    buffer_cb.borrow_mut().clear();
+        });
+    }
+
+    // Whenever the overlay gets its size, recompute the geometry and place the labels on
+    // its cells. Moving children while being allocated is deferred to an idle callback.
+    let label_chars = tokens.iter().map(|t| t.chars().count()).max().unwrap_or(0);
+    let font_fitter = Rc::new(FontFitter::new(&config.theme, label_chars));
+    let laid_out = Rc::new(Cell::new(false));
+    {
+        let source = source.clone();
+        let cells = cells.clone();
+        let cell_labels = Rc::clone(&cell_labels);
+        let geometry = Rc::clone(&geometry);
+        let laid_out = Rc::clone(&laid_out);
+        lines.connect_resize(move |area, width, height| {
+            let computed = update_geometry(&geometry, &source, area, width, height);
+            let cells = cells.clone();
+            let cell_labels = Rc::clone(&cell_labels);
+            let font_fitter = Rc::clone(&font_fitter);
+            let laid_out = Rc::clone(&laid_out);
+            glib::idle_add_local_once(move || {
+                for (label, rect) in cell_labels.borrow().iter().zip(computed.local_cells()) {
+                    cells.move_(&label.container, rect.x, rect.y);
+                    label.container.set_size_request(rect.width as i32, rect.height as i32);
+                }
+                let (cell_width, cell_height) = computed.min_cell_size();
+                let fits = font_fitter.fit(cell_width, cell_height);
+                for label in cell_labels.borrow().iter() {
+                    label.pill.set_visible(fits);
+                }
+                laid_out.set(true);
+            });
+        });
+    }
+
+    // Follow the pointer with a mark. The overlay only learns the pointer position once the
+    // pointer enters its surface, which happens as soon as it maps under the pointer.
+    let motion_controller = EventControllerMotion::new();
+    {
+        let cell_labels = Rc::clone(&cell_labels);
+        let geometry = Rc::clone(&geometry);
+        let mark_pointer_cell = move |x: f64, y: f64| {
+            let pointer_cell = geometry.borrow().as_ref().and_then(|g| g.local_cell_at(x, y));
+            for (index, label) in cell_labels.borrow().iter().enumerate() {
+                if pointer_cell == Some(index) {
+                    label.container.add_css_class("pointer-cell");
+                } else {
+                    label.container.remove_css_class("pointer-cell");
                 }
             }
-            Propagation::Proceed
+        };
+        let mark_on_enter = mark_pointer_cell.clone();
+        motion_controller.connect_enter(move |_controller, x, y| mark_on_enter(x, y));
+        motion_controller.connect_motion(move |_controller, x, y| mark_pointer_cell(x, y));
+    }
+    cells.add_controller(motion_controller);
+
+    let overlay = gtk4::Overlay::new();
+    overlay.set_child(Some(&cells));
+    overlay.add_overlay(&lines);
+    overlay.set_hexpand(true);
+    overlay.set_vexpand(true);
+
+    GridView {
+        widget: overlay.upcast(),
+        geometry,
+        show_input: Box::new(move |input: &str| {
+            for label in cell_labels.borrow().iter() {
+                label.show_input(input);
+            }
+        }),
+        laid_out,
+    }
+}
+
+/// What the canvas renderer draws besides the tokens.
+struct CanvasState {
+    input: RefCell<String>,
+    pointer_cell: Cell<Option<usize>>,
+    last_selection: Option<usize>,
+}
+
+/// Builds the grid view drawn on a single drawing area: cell backgrounds, labels and lines
+/// are painted in one pass from the geometry model, and the cell under the pointer is found
+/// by hit-testing the same model, so no widget is created per cell.
+fn build_canvas_view(
+    source: &GeometrySource,
+    tokens: &[String],
+    last_selection: Option<usize>,
+) -> GridView {
+    let config = &source.config;
+    let geometry: Rc<RefCell<Option<OverlayGeometry>>> = Rc::new(RefCell::new(None));
+    let state = Rc::new(CanvasState {
+        input: RefCell::new(String::new()),
+        pointer_cell: Cell::new(None),
+        last_selection,
+    });
+
+    let canvas = DrawingArea::new();
+    canvas.add_css_class("cell-canvas");
+    canvas.set_hexpand(true);
+    canvas.set_vexpand(true);
+    {
+        let source = source.clone();
+        let geometry = Rc::clone(&geometry);
+        canvas.connect_resize(move |area, width, height| {
+            update_geometry(&geometry, &source, area, width, height);
+        });
+    }
+    {
+        let geometry = Rc::clone(&geometry);
+        let theme = config.theme.clone();
+        let tokens = tokens.to_vec();
+        let state = Rc::clone(&state);
+        canvas.set_draw_func(move |_area, cr, _width, _height| {
+            if let Some(geometry) = geometry.borrow().as_ref() {
+                draw_canvas_cells(cr, geometry, &theme, &tokens, &state);
+                draw_grid_lines(cr, geometry, &theme);
+            }
+        });
+    }
+
+    let motion_controller = EventControllerMotion::new();
+    {
+        let geometry = Rc::clone(&geometry);
+        let state = Rc::clone(&state);
+        let canvas_for_motion = canvas.clone();
+        let mark_pointer_cell = move |x: f64, y: f64| {
+            let cell = geometry.borrow().as_ref().and_then(|g| g.local_cell_at(x, y));
+            if cell != state.pointer_cell.get() {
+                state.pointer_cell.set(cell);
+                canvas_for_motion.queue_draw();
+            }
+        };
+        let mark_on_enter = mark_pointer_cell.clone();
+        motion_controller.connect_enter(move |_controller, x, y| mark_on_enter(x, y));
+        motion_controller.connect_motion(move |_controller, x, y| mark_pointer_cell(x, y));
+    }
+    canvas.add_controller(motion_controller);
+
+    let canvas_for_input = canvas.clone();
+    GridView {
+        widget: canvas.upcast(),
+        geometry,
+        show_input: Box::new(move |input: &str| {
+            *state.input.borrow_mut() = input.to_string();
+            canvas_for_input.queue_draw();
+        }),
+        // Everything is drawn from the geometry in the same frame.
+        laid_out: Rc::new(Cell::new(true)),
+    }
+}
+
+/// Paints the backgrounds and labels of all cells, following the same rules as the CSS
+/// generated for the widget renderer: the typed prefix is highlighted, tokens not matching
+/// the input are dimmed, and the pointer cell and last selected cell are marked.
+fn draw_canvas_cells(
+    cr: &gtk4::cairo::Context,
+    geometry: &OverlayGeometry,
+    theme: &ConfTheme,
+    tokens: &[String],
+    state: &CanvasState,
+) {
+    let (cell_width, cell_height) = geometry.min_cell_size();
+    let chars = tokens.iter().map(|t| t.chars().count()).max().unwrap_or(0);
+    // Cells too small for any label still get their backgrounds.
+    let font_size = label_font_size(cell_width, cell_height, chars, theme);
+    let weight = if theme.font_weight.eq_ignore_ascii_case("bold") {
+        gtk4::cairo::FontWeight::Bold
+    } else {
+        gtk4::cairo::FontWeight::Normal
+    };
+    cr.select_font_face("Sans", gtk4::cairo::FontSlant::Normal, weight);
+    cr.set_font_size(font_size.max(1) as f64);
+    let Ok(font) = cr.font_extents() else {
+        return;
+    };
+
+    let input = state.input.borrow();
+    let rects = geometry.local_cells();
+    for (index, (rect, token)) in rects.iter().zip(tokens).enumerate() {
+        let matched = !input.is_empty() && token.starts_with(input.as_str());
+        let alpha = if !input.is_empty() && !matched { theme.dimmed_opacity } else { 1.0 };
+        let under_pointer = state.pointer_cell.get() == Some(index);
+        let (prefix, rest) = if matched { token.split_at(input.len()) } else { ("", token.as_str()) };
+        let advance = |text: &str| cr.text_extents(text).map(|e| e.x_advance()).unwrap_or(0.0);
+        let text_width = advance(prefix) + advance(rest);
+        let (center_x, center_y) = rect.center();
+        let text_x = center_x - text_width / 2.0;
+        let baseline = center_y + (font.ascent() - font.descent()) / 2.0;
+
+        if under_pointer {
+            set_source_color(cr, &theme.line_color, theme.line_opacity * alpha);
+            cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+        } else if theme.transparent_cells {
+            if font_size == 0 {
+                continue;
+            }
+            set_source_color(cr, &theme.background_color, theme.background_opacity * alpha);
+            let padding = LABEL_PILL_PADDING as f64;
+            cr.rectangle(
+                text_x - padding,
+                center_y - font.height() / 2.0,
+                text_width + 2.0 * padding,
+                font.height(),
+            );
+        } else {
+            set_source_color(cr, &theme.background_color, theme.background_opacity * alpha);
+            cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+        }
+        let _ = cr.fill();
+        if font_size == 0 {
+            continue;
+        }
+
+        let (text_color, text_opacity) = if under_pointer {
+            (&theme.background_color, theme.background_opacity)
+        } else if state.last_selection == Some(index) {
+            (&theme.line_color, theme.line_opacity)
+        } else {
+            (&theme.foreground_color, theme.foreground_opacity)
+        };
+        cr.move_to(text_x, baseline);
+        set_source_color(cr, &theme.highlight_color, theme.foreground_opacity * alpha);
+        let _ = cr.show_text(prefix);
+        set_source_color(cr, text_color, text_opacity * alpha);
+        let _ = cr.show_text(rest);
+    }
+}
+
+/// Options of this client invocation, as opposed to the configuration shared by the server.
+#[derive(Clone)]
+struct ClientOptions {
+    server_addr: String,
+    css_data: String,
+    /// When set, the time from this instant to the first painted frame is reported and
+    /// the client exits, to compare how fast the renderers present the overlay.
+    benchmark_start: Option<Instant>,
+    /// The rectangle of the output the grid covers instead of the whole output.
+    scope: Option<Rect>,
+    /// The region from `--region` the grid covers instead, in global layout coordinates.
+    /// It becomes the scope on the output showing it once the outputs are known.
+    region: Option<Rect>,
+    /// In picker mode, the format the selected rectangle is printed with instead of
+    /// moving the pointer, see `Rect::format`.
+    print_format: Option<String>,
+    /// When set, the pointer actions are appended to this file instead of performed.
+    record_pointer: Option<PathBuf>,
+}
+
+/// Reports the time to present once the window painted its first frame showing the
+/// finished grid, then exits. The widget renderer places and sizes its labels in an idle
+/// callback after the first allocation, so the frames painted before `laid_out` is set
+/// don't count.
+fn install_present_benchmark(
+    window: &ApplicationWindow,
+    start: Instant,
+    renderer: Renderer,
+    laid_out: Rc<Cell<bool>>,
+) {
+    window.connect_realize(move |win| {
+        let Some(frame_clock) = win.frame_clock() else {
+            return;
+        };
+        let laid_out = Rc::clone(&laid_out);
+        frame_clock.connect_after_paint(move |_clock| {
+            if !laid_out.get() {
+                return;
+            }
+            println!(
+                "Time to present with the {:?} renderer: {:.1} ms",
+                renderer,
+                start.elapsed().as_secs_f64() * 1000.0
+            );
+            std::process::exit(0);
         });
-        window.add_controller(key_controller2);
+    });
+}
+
+/// The GTK activation function builds the layer‑shell window with a grid view, rendered
+/// as configured by `grid.renderer`. Two key controllers are installed: one for exiting the
+/// app (using a configured shortcut) and one for handling token input. Where the cells lie
+/// on the output comes from the geometry model, so when a complete token is typed the
+/// pointer is moved to the center the model gives for the target cell.
+fn activate(application: &gtk4::Application, shared_data: SharedData, options: ClientOptions) {
+    let config = &shared_data.config;
+    let (scope, monitor) = match options.region {
+        Some(region) => region_scope(region),
+        None => (options.scope, None),
+    };
+    let window = build_overlay_window(application, config, &options.css_data, scope);
+    if let Some(monitor) = &monitor {
+        window.set_monitor(Some(monitor));
+    }
+
+    let headers = config.grid.headers
+        && config.grid.label_mode == LabelMode::Coordinates
+        && config.grid.is_uniform();
+    if config.grid.headers && config.grid.label_mode == LabelMode::Coordinates && !headers {
+        warn!("Headers are only shown on grids of equal cells; leaving them out.");
+    }
+    let source = GeometrySource {
+        config: config.clone(),
+        scope,
+        header_strip: if headers { header_strip(config) as f64 } else { 0.0 },
+    };
+    let cell_count = config.grid.cell_count();
+    let last_selection = shared_data.last_selection.filter(|&i| i < cell_count);
+    let view = match config.grid.renderer {
+        Renderer::Widgets => build_widget_view(&source, &shared_data.tokens, last_selection),
+        Renderer::Canvas => build_canvas_view(&source, &shared_data.tokens, last_selection),
+    };
+    if headers {
+        window.set_child(Some(&wrap_grid_with_headers(&view.widget, config)));
+    } else {
+        window.set_child(Some(&view.widget));
+    }
+
+    // A cancelled pick must not look like a selection to scripts.
+    let cancel_code = if options.print_format.is_some() { 1 } else { 0 };
+    install_exit_controller(&window, config.shortcut.exit_key, cancel_code);
+    if let Some(start) = options.benchmark_start {
+        install_present_benchmark(&window, start, config.grid.renderer, Rc::clone(&view.laid_out));
+    }
+
+    window.present();
+    window.grab_focus();
+
+    // Clone the window so it can be used within the closure.
+    let win_for_adjust = window.clone();
+    let adjust_config = config.clone();
+    let pointer: Rc<dyn PointerBackend> = Rc::from(pointer::create(options.record_pointer.as_deref()));
+    let server_addr = options.server_addr;
+    let print_format = options.print_format;
+    let profile = shared_data.profile.clone();
+    let geometry = view.geometry;
+
+    let jump_keys = keys_from_names(&config.shortcut.last_cell);
+    install_token_controller(&window, shared_data.tokens.clone(), last_selection, jump_keys, view.show_input, move |index| {
+        record_selection_on_server(&server_addr, index, profile.clone());
+        let geometry = geometry.borrow();
+        let Some(geometry) = geometry.as_ref() else {
+            error!("The grid has not been laid out yet.");
+            return false;
+        };
+        let cell = geometry.cells()[index];
+        if let Some(format) = &print_format {
+            println!("{}", cell.rect.format(format));
+            std::process::exit(0);
+        }
+        let (x, y) = cell.center;
+        info!(
+            index = index,
+            x = x,
+            y = y,
+            cell:? = (cell.rect.x, cell.rect.y, cell.rect.width, cell.rect.height);
+            "Moving the pointer"
+        );
+        if let Err(e) = pointer.move_to(x, y) {
+            error!("Failed to move the pointer: {}", e);
+            std::process::exit(1);
+        }
+        if adjust_config.nudge.enabled || adjust_config.scroll.enabled {
+            let (origin_x, origin_y) = geometry.surface_origin();
+            let origin = (origin_x as i32, origin_y as i32);
+            let output = geometry.output;
+            let pointer = Rc::clone(&pointer);
+            enter_adjust_mode(&win_for_adjust, &adjust_config, pointer, origin, output, x, y);
+            true
+        } else {
+            std::process::exit(0);
+        }
+    });
+}
+
+/// The GTK activation function for hint mode. Instead of a grid, a token label is placed
+/// over the top-left corner of every UI element discovered through AT-SPI, on an otherwise
+/// transparent overlay. Selecting a token moves the pointer to the element's center and,
+/// when `hint.click` is enabled, clicks it; otherwise the adjust mode may follow.
+fn activate_hints(
+    application: &gtk4::Application,
+    shared_data: SharedData,
+    options: ClientOptions,
+    mut targets: Vec<HintTarget>,
+) {
+    let config = &shared_data.config;
+    let window = build_overlay_window(application, config, &options.css_data, None);
+    window.add_css_class("hint-overlay");
+
+    let keys = SelectedKeys::new(&config.grid.key_left, &config.grid.key_right);
+    let capacity = token_capacity(&keys);
+    if targets.len() > capacity {
+        warn!(found = targets.len(), labelled = capacity; "More hint targets than tokens; labelling the first ones");
+        targets.truncate(capacity);
+    }
+    let tokens = generate_token_list(targets.len(), &keys);
+
+    // The overlay surface starts below/after the reserved bars, while AT-SPI reports
+    // screen coordinates, so labels are shifted back by the reserved edges.
+    let fixed = Fixed::new();
+    let mut hint_labels = Vec::new();
+    for (target, token) in targets.iter().zip(tokens.iter()) {
+        let hint_label = TokenLabel::new(token, "label-hint");
+        fixed.put(
+            &hint_label.container,
+            (target.x - config.reserved.left as i32) as f64,
+            (target.y - config.reserved.top as i32) as f64,
+        );
+        hint_labels.push(hint_label);
+    }
+    window.set_child(Some(&fixed));
+
+    let cancel_code = if options.print_format.is_some() { 1 } else { 0 };
+    install_exit_controller(&window, config.shortcut.exit_key, cancel_code);
+    if let Some(start) = options.benchmark_start {
+        // The hint labels are placed as they are created.
+        install_present_benchmark(&window, start, config.grid.renderer, Rc::new(Cell::new(true)));
+    }
+
+    window.present();
+    window.grab_focus();
+
+    let print_format = options.print_format;
+    let win_for_adjust = window.clone();
+    let adjust_config = config.clone();
+    let pointer: Rc<dyn PointerBackend> = Rc::from(pointer::create(options.record_pointer.as_deref()));
+    let show_input = move |input: &str| {
+        for label in &hint_labels {
+            label.show_input(input);
+        }
+    };
+    install_token_controller(&window, tokens, None, Vec::new(), show_input, move |index| {
+        let target = &targets[index];
+        info!(role = target.role.as_str(), name = target.name.as_str(); "Targeting a hint");
+        if let Some(format) = &print_format {
+            let rect = Rect::new(target.x as f64, target.y as f64, target.width as f64, target.height as f64);
+            println!("{}", rect.format(format));
+            std::process::exit(0);
+        }
+        let (x, y) = target.center();
+        info!(index = index, x = x, y = y; "Moving the pointer");
+        if let Err(e) = pointer.move_to(x, y) {
+            error!("Failed to move the pointer: {}", e);
+            std::process::exit(1);
+        }
+        if adjust_config.hint.click {
+            if let Err(e) = pointer.click(adjust_config.hint.click_button) {
+                error!("Failed to click: {}", e);
+                std::process::exit(1);
+            }
+        } else if adjust_config.nudge.enabled || adjust_config.scroll.enabled {
+            // The hint overlay starts after the reserved bars.
+            let origin = (adjust_config.reserved.left as i32, adjust_config.reserved.top as i32);
+            // Without a known output, the pointer is only kept off negative coordinates.
+            let unbounded = Rect::new(0.0, 0.0, i32::MAX as f64, i32::MAX as f64);
+            let output = monitor_of(&win_for_adjust).map_or(unbounded, |(output, _)| output);
+            let pointer = Rc::clone(&pointer);
+            enter_adjust_mode(&win_for_adjust, &adjust_config, pointer, origin, output, x, y);
+            return true;
+        }
+        std::process::exit(0);
+    });
+}
+
+/// Asks the compositor which window is focused, so the server can pick its profile.
+/// Profiles are optional, so failures are only reported.
+fn retrieve_focused_window(query_name: &str) -> Option<FocusedWindow> {
+    let query = focus::query_by_name(query_name)?;
+    match query.focused_window() {
+        Ok(window) => {
+            debug!("Focused window: {:?}", window);
+            window
+        }
+        Err(e) => {
+            debug!("Failed to query the focused window: {}", e);
+            None
+        }
+    }
+}
+
+/// Parses the `--region` argument, reading the region from stdin for `-`, so the output
+/// of `slurp` can be piped in. Exits on an invalid region, since a script asked for it.
+fn read_region(arg: &str) -> Rect {
+    let region = if arg == "-" {
+        let mut line = String::new();
+        if let Err(e) = std::io::stdin().read_line(&mut line) {
+            error!("Failed to read the region from stdin: {}", e);
+            std::process::exit(1);
+        }
+        line
+    } else {
+        arg.to_string()
+    };
+    parse_region(&region).unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Queries the accessibility bus for the selectable elements of the focused window.
+/// Exits with an error when AT-SPI is unavailable or nothing selectable was found.
+fn retrieve_hint_targets() -> Vec<HintTarget> {
+    let targets = hint::a11y_bus_address()
+        .and_then(|address| hint::connect(&address))
+        .and_then(|conn| hint::collect_targets(&conn, None));
+    match targets {
+        Ok(targets) if !targets.is_empty() => targets,
+        Ok(_) => {
+            error!("No selectable elements found in the focused window.");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            error!("Failed to query the accessibility bus: {}", e);
+            std::process::exit(1);
+        }
     }
 }
 
 fn main() {
+    let start = Instant::now();
     let name = "gtk-cursor-navigator";
 
     APP_NAME.set(name).expect("APP_NAME already initialized");
+    // The client shares the configuration directory of the server, for `style.css`.
     CONF_DIR_DEFAULT
-        .set(PathBuf::from(format!("~/.config/{}", name)))
+        .set(user_config_dir())
         .expect("CONF_DIR_DEFAULT already initialized");
     CONF_FILE_SUFFIX
         .set(".toml")
@@ -290,29 +1405,180 @@ fn main() {
                 .value_parser(value_parser!(String))
                 .default_value("127.0.0.1:7878"),
         )
+        .arg(
+            Arg::new("style")
+                .long("style")
+                .value_name("STYLE")
+                .help("Sets the user stylesheet applied on top of the theme (default: style.css in the config directory)")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("hint")
+                .long("hint")
+                .help("Label the UI elements of the focused window found through AT-SPI instead of a grid")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .value_name("PROFILE")
+                .help("Uses the named profile of the server config instead of the one matching the focused window")
+                .value_parser(value_parser!(String)),
+        )
+        .arg(
+            Arg::new("focus_query")
+                .long("focus-query")
+                .value_name("QUERY")
+                .help("How the focused window is found for profiles and --window: auto, sway, hyprland, toplevel or none")
+                .value_parser(["auto", "sway", "hyprland", "toplevel", "none"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::new("window")
+                .long("window")
+                .help("Cover only the focused window with the grid, as with cover_screen = false")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("rect")
+                .long("rect")
+                .value_name("X,Y,W,H")
+                .help("Cover only this rectangle of the output with the grid")
+                .value_parser(value_parser!(Rect)),
+        )
+        .arg(
+            Arg::new("region")
+                .long("region")
+                .value_name("X,Y WxH")
+                .help("Cover only this region with the grid, in the format slurp prints; - reads it from stdin")
+                .value_parser(value_parser!(String))
+                .conflicts_with("rect"),
+        )
+        .arg(
+            Arg::new("print")
+                .long("print")
+                .help("Print the selected cell's center as x,y instead of moving the pointer; exit with 1 when cancelled")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Like --print, with a format of %x %y %w %h (the cell) and %X %Y (its center), e.g. \"%x,%y %wx%h\" for grim -g")
+                .value_parser(value_parser!(String)),
+        )
+        .arg(
+            Arg::new("renderer")
+                .long("renderer")
+                .value_name("RENDERER")
+                .help("Overrides the configured grid renderer: widgets or canvas")
+                .value_parser(["widgets", "canvas"]),
+        )
+        .arg(
+            Arg::new("benchmark")
+                .long("benchmark")
+                .help("Print the time until the overlay is first painted, then exit")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("record_pointer")
+                .long("record-pointer")
+                .value_name("FILE")
+                .help("Append the pointer actions to FILE as JSON lines instead of performing them")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("log_level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("Overrides the log level of the configuration")
+                .value_parser(["error", "warn", "info", "debug", "trace"]),
+        )
         .arg(
             Arg::new("debug")
                 .long("debug")
-                .help("Enable debug logging")
+                .help("Enable debug logging, like --log-level debug")
                 .action(clap::ArgAction::SetTrue),
         );
     let matches = command.get_matches();
 
-    env_logger::init();
-    if matches.get_flag("debug") {
-        debug!("Client debug mode enabled");
-    }
+    // Until the server sends the configuration, log to stderr only.
+    let log_level = matches.get_one::<String>("log_level")
+        .and_then(|level| level.parse::<LevelFilter>().ok())
+        .or_else(|| matches.get_flag("debug").then_some(LevelFilter::Debug));
+    logging::init(name, &ConfLog { file: false, ..ConfLog::default() }, log_level);
 
-    let server_addr = matches.get_one::<String>("server").unwrap();
+    let server_addr = matches.get_one::<String>("server").unwrap().clone();
     debug!("Connecting to server at {}", server_addr);
-    let shared_data = retrieve_shared_data_from_server(server_addr);
+    let profile = matches.get_one::<String>("profile").cloned();
+    let focus_query = matches.get_one::<String>("focus_query").unwrap();
+    let mut shared_data = retrieve_shared_data_from_server(&server_addr, profile.clone(), None);
+    // Finding the focused window runs a compositor tool, so it is only done when a profile
+    // could match it. A profile active on the server comes without profiles.
+    let mut window = None;
+    let mut window_queried = false;
+    if profile.is_none() && shared_data.config.has_window_patterns() {
+        window = retrieve_focused_window(focus_query);
+        window_queried = true;
+        if window.is_some() {
+            shared_data = retrieve_shared_data_from_server(&server_addr, None, window.clone());
+        }
+    }
+    logging::init(name, &shared_data.config.log, log_level);
     debug!("Shared data retrieved: {:?}", shared_data);
 
+    // The window scope needs the focused window's geometry, which only some queries report.
+    let rect = matches.get_one::<Rect>("rect").copied();
+    let region = matches.get_one::<String>("region").map(|arg| read_region(arg));
+    let scope = match rect {
+        Some(rect) => Some(rect),
+        None if region.is_some() => None,
+        None if matches.get_flag("window") || !shared_data.config.grid.cover_screen => {
+            if !window_queried {
+                window = retrieve_focused_window(focus_query);
+            }
+            let rect = window.and_then(|window| window.rect);
+            if rect.is_none() {
+                warn!("The focused window's geometry is unknown, covering the whole output.");
+            }
+            rect
+        }
+        None => None,
+    };
+    match matches.get_one::<String>("renderer").map(String::as_str) {
+        Some("widgets") => shared_data.config.grid.renderer = Renderer::Widgets,
+        Some("canvas") => shared_data.config.grid.renderer = Renderer::Canvas,
+        _ => {}
+    }
+
     let css_data = generate_css_from_theme(&shared_data.config.theme);
+    let user_style = matches.get_one::<PathBuf>("style").cloned().unwrap_or_else(|| {
+        let conf_dir = CONF_DIR_DEFAULT.get().unwrap();
+        let style_file = format!("style{}", STYLE_FILE_SUFFIX.get().unwrap());
+        expand_path(conf_dir.to_str().unwrap()).join(style_file)
+    });
+
+    // Hint targets are queried before the overlay maps, so it doesn't hide the window.
+    let hint_targets = matches.get_flag("hint").then(retrieve_hint_targets);
+
+    let options = ClientOptions {
+        server_addr,
+        css_data,
+        benchmark_start: matches.get_flag("benchmark").then_some(start),
+        scope,
+        region,
+        print_format: matches.get_one::<String>("format").cloned()
+            .or_else(|| matches.get_flag("print").then(|| "%X,%Y".to_string())),
+        record_pointer: matches.get_one::<PathBuf>("record_pointer").cloned(),
+    };
 
     let app = Application::new(Some("sh.wmww.gtk-layer-example"), Default::default());
     app.connect_activate(move |app| {
-        activate(app, shared_data.clone(), css_data.clone());
+        load_user_stylesheet(&user_style);
+        match &hint_targets {
+            Some(targets) => activate_hints(app, shared_data.clone(), options.clone(), targets.clone()),
+            None => activate(app, shared_data.clone(), options.clone()),
+        }
     });
     app.run_with_args(&[env::args().next().unwrap()]);
 }
@@ -0,0 +1,216 @@
+//! Pointer backends performing the actual pointer actions for the overlay.
+//!
+//! The overlay only computes where the pointer should go; a backend moves it there.
+//! The default backend drives `ydotool`, which works independently of the compositor.
+//! The recording backend only writes the actions down, for tests and dry runs.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::mem;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A mouse button that can be clicked through a pointer backend.
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PointerButton {
+    #[default]
+    Left,
+    Right,
+    Middle,
+}
+
+/// The direction of a scroll wheel event.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollAxis {
+    Vertical,
+    Horizontal,
+}
+
+/// How far to scroll; positive values scroll down or right.
+/// - `Discrete`: whole wheel detents ("clicks").
+/// - `HighResolution`: fractions of a detent in 1/120 units, as in `REL_WHEEL_HI_RES`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollAmount {
+    Discrete(i32),
+    HighResolution(i32),
+}
+
+/// Units of a high resolution scroll amount making up one wheel detent.
+pub const HIGH_RESOLUTION_PER_DETENT: i32 = 120;
+
+/// Actions the overlay can ask of the pointer.
+/// Coordinates are absolute pixels on the output; scrolling happens at the pointer.
+pub trait PointerBackend {
+    fn move_to(&self, x: i32, y: i32) -> io::Result<()>;
+    fn click(&self, button: PointerButton) -> io::Result<()>;
+    fn scroll(&self, axis: ScrollAxis, amount: ScrollAmount) -> io::Result<()>;
+}
+
+/// Creates the backend of this client: `ydotool`, or a `Recorder` appending to `record`
+/// when the actions should only be written down.
+pub fn create(record: Option<&Path>) -> Box<dyn PointerBackend> {
+    match record {
+        Some(path) => Box::new(Recorder::new(path)),
+        None => Box::new(Ydotool::default()),
+    }
+}
+
+/// Backend driving the `ydotool` command, which must have its daemon running.
+/// The command only sends whole wheel detents, so wheel events are written straight to the
+/// daemon's socket instead: `REL_WHEEL_HI_RES` for every amount, and `REL_WHEEL` whenever a
+/// whole detent has been collected on an axis, as high resolution mice do.
+pub struct Ydotool {
+    socket: PathBuf,
+    /// High resolution units per axis not sent as a whole detent yet.
+    pending: Cell<(i32, i32)>,
+}
+
+impl Default for Ydotool {
+    /// Uses the daemon's socket the way the `ydotool` command finds it.
+    fn default() -> Self {
+        let socket = env::var_os("YDOTOOL_SOCKET")
+            .map_or_else(|| PathBuf::from("/tmp/.ydotool_socket"), PathBuf::from);
+        Ydotool::new(socket)
+    }
+}
+
+// Event types and codes of `linux/input-event-codes.h`.
+const EV_SYN: u16 = 0x00;
+const EV_REL: u16 = 0x02;
+const SYN_REPORT: u16 = 0x00;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const REL_WHEEL_HI_RES: u16 = 0x0b;
+const REL_HWHEEL_HI_RES: u16 = 0x0c;
+
+impl Ydotool {
+    /// Sends the wheel events to the daemon listening on `socket`.
+    pub fn new(socket: PathBuf) -> Self {
+        Ydotool { socket, pending: Cell::new((0, 0)) }
+    }
+
+    fn run(args: &[String]) -> io::Result<()> {
+        let status = Command::new("ydotool").args(args).status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("ydotool {} failed: {}", args[0], status)))
+        }
+    }
+
+    /// Sends `(type, code, value)` input events to the daemon, which writes them to its
+    /// virtual device as they are.
+    fn emit(&self, events: &[(u16, u16, i32)]) -> io::Result<()> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&self.socket)?;
+        for &(kind, code, value) in events {
+            // A `struct input_event` with a zero timestamp, which the kernel fills in.
+            let mut event = vec![0; mem::size_of::<libc::timeval>()];
+            event.extend_from_slice(&kind.to_ne_bytes());
+            event.extend_from_slice(&code.to_ne_bytes());
+            event.extend_from_slice(&value.to_ne_bytes());
+            socket.send(&event)?;
+        }
+        Ok(())
+    }
+}
+
+impl PointerBackend for Ydotool {
+    fn move_to(&self, x: i32, y: i32) -> io::Result<()> {
+        // ydotool mousemove --absolute -x <X> -y <Y>
+        Self::run(&[
+            "mousemove".to_string(),
+            "--absolute".to_string(),
+            "-x".to_string(),
+            x.to_string(),
+            "-y".to_string(),
+            y.to_string(),
+        ])
+    }
+
+    fn click(&self, button: PointerButton) -> io::Result<()> {
+        // The low nibble selects the button, 0xC0 means press then release.
+        let code = match button {
+            PointerButton::Left => "0xC0",
+            PointerButton::Right => "0xC1",
+            PointerButton::Middle => "0xC2",
+        };
+        Self::run(&["click".to_string(), code.to_string()])
+    }
+
+    fn scroll(&self, axis: ScrollAxis, amount: ScrollAmount) -> io::Result<()> {
+        let units = match amount {
+            ScrollAmount::Discrete(detents) => detents * HIGH_RESOLUTION_PER_DETENT,
+            ScrollAmount::HighResolution(units) => units,
+        };
+        // The kernel counts vertical wheel motion upwards.
+        let (mut vertical, mut horizontal) = self.pending.get();
+        let (pending, units, detent_code, unit_code) = match axis {
+            ScrollAxis::Vertical => (&mut vertical, -units, REL_WHEEL, REL_WHEEL_HI_RES),
+            ScrollAxis::Horizontal => (&mut horizontal, units, REL_HWHEEL, REL_HWHEEL_HI_RES),
+        };
+        *pending += units;
+        let detents = *pending / HIGH_RESOLUTION_PER_DETENT;
+        *pending %= HIGH_RESOLUTION_PER_DETENT;
+        self.pending.set((vertical, horizontal));
+
+        let mut events = vec![(EV_REL, unit_code, units)];
+        if detents != 0 {
+            events.push((EV_REL, detent_code, detents));
+        }
+        events.push((EV_SYN, SYN_REPORT, 0));
+        self.emit(&events)
+    }
+}
+
+/// An action asked of a pointer backend, as written by `Recorder`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PointerAction {
+    MoveTo { x: i32, y: i32 },
+    Click { button: PointerButton },
+    Scroll { axis: ScrollAxis, amount: ScrollAmount },
+}
+
+/// Backend appending every action as a line of JSON to a file instead of performing it.
+pub struct Recorder {
+    path: PathBuf,
+}
+
+impl Recorder {
+    pub fn new(path: &Path) -> Self {
+        Recorder { path: path.to_path_buf() }
+    }
+
+    fn record(&self, action: PointerAction) -> io::Result<()> {
+        let mut line = serde_json::to_string(&action).map_err(io::Error::other)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+}
+
+impl PointerBackend for Recorder {
+    fn move_to(&self, x: i32, y: i32) -> io::Result<()> {
+        self.record(PointerAction::MoveTo { x, y })
+    }
+
+    fn click(&self, button: PointerButton) -> io::Result<()> {
+        self.record(PointerAction::Click { button })
+    }
+
+    fn scroll(&self, axis: ScrollAxis, amount: ScrollAmount) -> io::Result<()> {
+        self.record(PointerAction::Scroll { axis, amount })
+    }
+}
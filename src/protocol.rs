@@ -0,0 +1,95 @@
+//! Requests exchanged between the client and the server over TCP.
+//!
+//! A client writes one JSON encoded request terminated by a newline, and the server
+//! answers with one JSON document before closing the connection.
+
+use crate::focus::FocusedWindow;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::time::SystemTime;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum Request {
+    /// Ask for the configuration, the tokens and the last selected cell, for the profile
+    /// named `profile`, or else the profile matching the focused `window`, or else the main
+    /// configuration. Answered with `SharedData`.
+    SharedData {
+        #[serde(default)]
+        profile: Option<String>,
+        #[serde(default)]
+        window: Option<FocusedWindow>,
+    },
+    /// Remember the cell a client selected under `profile`, so the next overlay for it can
+    /// show it and jump back to it. Answered with `null`.
+    RecordSelection {
+        index: usize,
+        #[serde(default)]
+        profile: Option<String>,
+    },
+    /// Ask how the server is doing. Answered with `ServerStatus`.
+    Status,
+    /// Read the configuration file again and regenerate the tokens of every profile.
+    /// The previous configuration is kept when the file can't be read. Answered with
+    /// `ServerStatus`.
+    Reload,
+    /// Make `profile` the active profile, used for every client not asking for one by
+    /// name, or go back to matching windows for `None`. An unknown profile leaves the
+    /// active one unchanged and is reported as the last error. Answered with `ServerStatus`.
+    SetProfile {
+        #[serde(default)]
+        profile: Option<String>,
+    },
+    /// Ask the server to stop once it answered. Answered with `null`.
+    Shutdown,
+}
+
+/// What the server reports about itself, for diagnosing a server that seems out of
+/// date with its configuration file. The uptime is in seconds; modification times are
+/// kept at the precision of the file system, so a change within the second is noticed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerStatus {
+    pub uptime: u64,
+    /// The files the configuration was loaded from, lowest precedence first.
+    pub config_files: Vec<String>,
+    /// Latest modification time of the configuration files when they were last loaded.
+    pub loaded_config_modified: Option<SystemTime>,
+    /// Latest modification time of the configuration files now.
+    pub config_modified: Option<SystemTime>,
+    pub token_count: usize,
+    pub profiles: Vec<String>,
+    pub active_profile: Option<String>,
+    pub clients_served: u64,
+    pub reloads: u64,
+    pub last_error: Option<String>,
+}
+
+impl ServerStatus {
+    /// Whether a configuration file changed after the configuration was loaded.
+    pub fn config_is_stale(&self) -> bool {
+        self.config_modified.is_some() && self.config_modified != self.loaded_config_modified
+    }
+}
+
+/// Sends `request` to the server at `server_addr` and returns the raw answer.
+pub fn send_request(server_addr: &str, request: &Request) -> io::Result<String> {
+    let mut stream = TcpStream::connect(server_addr)?;
+    let mut line = serde_json::to_string(request).map_err(io::Error::other)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+    let mut buffer = String::new();
+    BufReader::new(stream).read_to_string(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Reads the request a client sent on `stream`. A connection closed without sending
+/// anything, such as a port probe, gives an `UnexpectedEof` error.
+pub fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut line = String::new();
+    if BufReader::new(stream).read_line(&mut line)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
@@ -0,0 +1,92 @@
+//! JSON Schema of the configuration, for editors validating and completing config files,
+//! e.g. Taplo and Even Better TOML.
+//!
+//! The schema is derived from the `Conf` structs with `schemars`, so types, defaults and
+//! enum values follow the code, and descriptions are the fields' doc comments.
+
+use crate::conf::Conf;
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde_json::Value;
+
+/// GDK key names offered for completion; any other GDK key name is accepted too.
+const COMMON_KEY_NAMES: [&str; 20] = [
+    "Escape", "Return", "KP_Enter", "Tab", "space", "BackSpace", "Delete", "Insert", "Home",
+    "End", "Page_Up", "Page_Down", "Left", "Right", "Up", "Down", "comma", "period", "slash",
+    "semicolon",
+];
+
+/// The JSON Schema of config files.
+pub fn conf_schema() -> Value {
+    let mut schema = schemars::schema_for!(Conf).to_value();
+    if let Some(properties) = schema.get_mut("properties") {
+        // Read while merging the config files rather than by `Conf`.
+        properties["include"] = serde_json::json!({
+            "description": "Other config files, relative to this one, merged before it.",
+            "type": "array",
+            "items": { "type": "string" }
+        });
+    }
+    shorten_floats(&mut schema);
+    schema
+}
+
+/// Writes the `f32` defaults with their shortest decimal, 0.8 rather than the digits of
+/// their `f64` widening.
+fn shorten_floats(value: &mut Value) {
+    match value {
+        Value::Number(number) => {
+            if let Some(f) = number.as_f64().filter(|_| number.is_f64())
+                && (f as f32) as f64 == f
+                && let Ok(short) = (f as f32).to_string().parse::<f64>()
+            {
+                *value = short.into();
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(shorten_floats),
+        Value::Object(object) => object.values_mut().for_each(shorten_floats),
+        _ => {}
+    }
+}
+
+/// A list of GDK key names, e.g. "Escape" or "Page_Up".
+pub fn key_names(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "array",
+        "items": {
+            "anyOf": [
+                { "enum": COMMON_KEY_NAMES },
+                { "type": "string", "pattern": "^[A-Za-z0-9_]+$" }
+            ]
+        }
+    })
+}
+
+/// Modifier names accepted by the nudge mode, in any case.
+const MODIFIERS: [&str; 5] = ["Shift", "Control", "Ctrl", "Alt", "Super"];
+
+/// A modifier name, as accepted by the nudge mode: any case of `MODIFIERS`. JSON Schema
+/// patterns have no case-insensitive flag, so every letter gets a class of both cases.
+pub fn modifier(_: &mut SchemaGenerator) -> Schema {
+    let names: Vec<String> = MODIFIERS
+        .iter()
+        .map(|name| {
+            name.chars()
+                .map(|c| format!("[{}{}]", c.to_ascii_uppercase(), c.to_ascii_lowercase()))
+                .collect()
+        })
+        .collect();
+    json_schema!({
+        "type": "string",
+        "pattern": format!("^({})$", names.join("|")),
+        "examples": MODIFIERS
+    })
+}
+
+/// A CSS font weight, by name or number. The value is passed to the stylesheet as is, so
+/// any string is accepted; the examples are offered for completion.
+pub fn font_weight(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "string",
+        "examples": ["normal", "bold", "bolder", "lighter", "400", "600", "700"]
+    })
+}
@@ -0,0 +1,56 @@
+//! The example `config.toml` generated by `init-config` from the `Conf` definitions.
+
+use std::path::PathBuf;
+
+use gtk_cursor_navigator::conf::{load_config, Conf, PathBufExt};
+use gtk_cursor_navigator::conf_doc::commented_default;
+use gtk_cursor_navigator::schema::conf_schema;
+
+fn example_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("config.toml")
+}
+
+#[test]
+fn example_config_reads_back_as_the_defaults() {
+    let (conf, read) = load_config(&[example_path()]).unwrap();
+    assert_eq!(read, [example_path()]);
+    assert_eq!(
+        toml::Value::try_from(conf).unwrap(),
+        toml::Value::try_from(Conf::default()).unwrap()
+    );
+    assert_eq!(
+        toml::Value::try_from(example_path().read_config()).unwrap(),
+        toml::Value::try_from(Conf::default()).unwrap()
+    );
+    let generated: Conf = toml::from_str(&commented_default()).unwrap();
+    assert_eq!(
+        toml::Value::try_from(generated).unwrap(),
+        toml::Value::try_from(Conf::default()).unwrap()
+    );
+}
+
+#[test]
+fn example_config_is_up_to_date() {
+    let example = std::fs::read_to_string(example_path()).unwrap();
+    assert!(
+        example == commented_default(),
+        "config.toml is out of date; regenerate it with `gtk-cursor-navigator-server init-config --path config.toml --force`"
+    );
+}
+
+#[test]
+fn every_setting_is_documented() {
+    let schema = conf_schema();
+    for (name, definition) in schema["$defs"].as_object().unwrap() {
+        let Some(fields) = definition["properties"].as_object() else {
+            continue;
+        };
+        for (field, property) in fields {
+            assert!(property["description"].is_string(), "{}.{} has no documentation", name, field);
+        }
+    }
+    let example = commented_default();
+    for rust_name in ["::", "`LabelMode`", "`Renderer`", "`FontSizing`", "`LineStyle`", "Any other key"] {
+        assert!(!example.contains(rust_name), "config.toml mentions {}", rust_name);
+    }
+}
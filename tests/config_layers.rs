@@ -0,0 +1,117 @@
+//! Layered configuration: merging files, includes and drop-ins, and environment overrides.
+
+mod common;
+
+use std::fs;
+
+use common::scratch_dir;
+use gtk_cursor_navigator::conf::{
+    apply_env_overrides, config_files, merge_config_files, merge_values, Conf,
+};
+
+fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+#[test]
+fn tables_merge_key_by_key() {
+    let mut base: toml::Value = toml::from_str("[grid]\nrows = 4\ncolumns = 6\n[theme]\nfont_size = 20\n").unwrap();
+    let overlay: toml::Value = toml::from_str("[grid]\nrows = 8\nrow_sizes = [1, 2]\n").unwrap();
+    merge_values(&mut base, overlay);
+    assert_eq!(base["grid"]["rows"].as_integer(), Some(8));
+    assert_eq!(base["grid"]["columns"].as_integer(), Some(6));
+    assert_eq!(base["grid"]["row_sizes"].as_array().map(Vec::len), Some(2));
+    assert_eq!(base["theme"]["font_size"].as_integer(), Some(20));
+}
+
+#[test]
+fn drop_ins_follow_the_main_file_in_name_order() {
+    let dir = scratch_dir("layers");
+    fs::create_dir_all(dir.join("conf.d")).unwrap();
+    let main = dir.join("config.toml");
+    fs::write(&main, "[grid]\nrows = 4\ncolumns = 6\n").unwrap();
+    fs::write(dir.join("conf.d/20-theme.toml"), "[theme]\nfont_size = 30\n[grid]\nrows = 7\n").unwrap();
+    fs::write(dir.join("conf.d/10-grid.toml"), "[grid]\nrows = 5\ncolumns = 9\n").unwrap();
+    fs::write(dir.join("conf.d/notes.txt"), "not a config").unwrap();
+
+    let files = config_files(Some(&main));
+    let names: Vec<_> = files.iter().map(|file| file.file_name().unwrap().to_owned()).collect();
+    assert_eq!(names, ["config.toml", "10-grid.toml", "20-theme.toml"]);
+
+    let conf: Conf = merge_config_files(&files).unwrap().try_into().unwrap();
+    assert_eq!((conf.grid.rows, conf.grid.columns), (7, 9));
+    assert_eq!(conf.theme.font_size, 30);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn environment_overrides_settings() {
+    let mut value: toml::Value = toml::from_str("[grid]\nrows = 4\n").unwrap();
+    apply_env_overrides(
+        &mut value,
+        vars(&[
+            ("GCN_GRID_ROWS", "8"),
+            ("GCN_GRID_KEY_LEFT", "A S D F"),
+            ("GCN_GRID_ROW_SIZES", "[0.5, 40]"),
+            ("GCN_THEME_LINE_COLOR", "#ff0000"),
+            ("GCN_NUDGE_ENABLED", "false"),
+            ("GCN_NO_SUCH_SETTING", "1"),
+            ("GCN_PROFILES_X", "1"),
+            ("HOME", "/home/me"),
+        ]),
+    );
+    let conf: Conf = value.try_into().unwrap();
+    assert_eq!(conf.grid.rows, 8);
+    assert_eq!(conf.grid.key_left, "A S D F");
+    assert_eq!(conf.grid.row_sizes.len(), 2);
+    assert_eq!(conf.theme.line_color, "#ff0000");
+    assert!(!conf.nudge.enabled);
+    assert!(conf.profiles.is_empty());
+}
+
+#[test]
+fn included_files_are_overridden_by_the_including_file() {
+    let dir = scratch_dir("include");
+    fs::create_dir_all(dir.join("shared")).unwrap();
+    let main = dir.join("config.toml");
+    fs::write(&main, "include = [\"shared/docked.toml\"]\n[grid]\nrows = 4\n").unwrap();
+    fs::write(dir.join("shared/docked.toml"), "include = [\"base.toml\"]\n[grid]\nrows = 9\ncolumns = 12\n").unwrap();
+    fs::write(dir.join("shared/base.toml"), "[grid]\ncolumns = 3\n[theme]\nfont_size = 18\n").unwrap();
+
+    let conf: Conf = merge_config_files(std::slice::from_ref(&main)).unwrap().try_into().unwrap();
+    assert_eq!((conf.grid.rows, conf.grid.columns), (4, 12));
+    assert_eq!(conf.theme.font_size, 18);
+
+    fs::write(dir.join("shared/base.toml"), "include = [\"../config.toml\"]\n").unwrap();
+    let error = merge_config_files(&[main]).unwrap_err();
+    assert!(error.contains("includes itself"), "{}", error);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn legacy_server_config_is_read_without_a_user_config() {
+    let dir = scratch_dir("legacy");
+    fs::create_dir_all(dir.join("gtk-cursor-navigator-server")).unwrap();
+    let legacy = dir.join("gtk-cursor-navigator-server/config.toml");
+    fs::write(&legacy, "[grid]\nrows = 3\n").unwrap();
+    let dump = || {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_gtk-cursor-navigator-server"))
+            .args(["config", "dump"])
+            .env("XDG_CONFIG_HOME", &dir)
+            .env("XDG_CONFIG_DIRS", dir.join("no-system-dir"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let text = dump();
+    assert!(text.contains(&format!("# From {}", legacy.display())), "{}", text);
+    assert!(text.contains("rows = 3"), "{}", text);
+
+    fs::create_dir_all(dir.join("gtk-cursor-navigator")).unwrap();
+    fs::write(dir.join("gtk-cursor-navigator/config.toml"), "[grid]\nrows = 7\n").unwrap();
+    let text = dump();
+    assert!(!text.contains(&legacy.display().to_string()), "{}", text);
+    assert!(text.contains("rows = 7"), "{}", text);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,165 @@
+//! Runs the server and the client inside a headless sway and types a token with `wtype`.
+//! The client records the pointer actions with `--record-pointer`, so the test checks the
+//! coordinates the pointer would have been moved to.
+//!
+//! sway renders with pixman and GTK with cairo, so no GPU is needed. Needs `sway` and
+//! `wtype`, and the binaries built, hence ignored by default; once asked for, it fails
+//! when they are missing:
+//!     cargo build && cargo test --test e2e -- --ignored
+
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{free_port, scratch_dir, wait_for_listener, Reaper};
+use gtk_cursor_navigator::pointer::PointerAction;
+
+fn found_in_path(program: &str) -> bool {
+    Command::new("sh")
+        .args(["-c", &format!("command -v {}", program)])
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Waits for a file in `dir` whose name satisfies `wanted`.
+fn wait_for_file(dir: &Path, wanted: impl Fn(&str) -> bool) -> PathBuf {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let found = fs::read_dir(dir).unwrap().flatten().find(|entry| {
+            entry.file_name().to_str().is_some_and(&wanted)
+        });
+        if let Some(entry) = found {
+            return entry.path();
+        }
+        assert!(Instant::now() < deadline, "Timed out waiting for a file in {:?}", dir);
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+const CONFIG: &str = r#"
+[grid]
+rows = 4
+columns = 6
+label_mode = "coordinates"
+key_left = "A S D F"
+key_right = "J K L U I O"
+
+[nudge]
+enabled = false
+
+[scroll]
+enabled = false
+"#;
+
+/// The label of the cell in row 3 and column 1, with the keys of `CONFIG`.
+const TOKEN: &str = "fk";
+
+/// The center of that cell on a 1280x720 output. Rows are 180 pixels high; columns are
+/// 213 pixels wide, plus one for the first two columns taking the 2 pixels left over, so
+/// column 1 spans 214..428.
+const CENTER: (i32, i32) = (321, 630);
+
+#[test]
+#[ignore = "needs sway, wtype and the built binaries"]
+fn typing_a_token_moves_the_pointer_to_the_cell_center() {
+    for program in ["sway", "wtype"] {
+        assert!(found_in_path(program), "{} not found in PATH", program);
+    }
+
+    let dir = scratch_dir("e2e");
+    let runtime_dir = dir.join("runtime");
+    fs::create_dir_all(&runtime_dir).unwrap();
+    let record_file = dir.join("pointer.jsonl");
+    let config_file = dir.join("config.toml");
+    fs::write(&config_file, CONFIG).unwrap();
+    let sway_config = dir.join("sway.conf");
+    fs::write(&sway_config, "output * resolution 1280x720\n").unwrap();
+
+    let _sway = Reaper(
+        Command::new("sway")
+            .args(["--config", sway_config.to_str().unwrap()])
+            .env("XDG_RUNTIME_DIR", &runtime_dir)
+            .env("WLR_BACKENDS", "headless")
+            .env("WLR_RENDERER", "pixman")
+            .env("WLR_LIBINPUT_NO_DEVICES", "1")
+            .env_remove("WAYLAND_DISPLAY")
+            .env_remove("DISPLAY")
+            .env_remove("SWAYSOCK")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to start sway"),
+    );
+    let display = wait_for_file(&runtime_dir, |name| name.starts_with("wayland-") && !name.ends_with(".lock"));
+    let display = display.file_name().unwrap().to_owned();
+
+    let port = free_port();
+    let server_addr = format!("127.0.0.1:{}", port);
+    let _server = Reaper(
+        Command::new(env!("CARGO_BIN_EXE_gtk-cursor-navigator-server"))
+            .args(["--config", config_file.to_str().unwrap(), "--port", &port.to_string()])
+            .env("XDG_RUNTIME_DIR", &runtime_dir)
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("Failed to start the server"),
+    );
+    wait_for_listener(&server_addr);
+
+    // Keys typed before the overlay is mapped are lost, so start over with a fresh client
+    // until one of them picks the cell.
+    let mut moved = None;
+    for _ in 0..5 {
+        let mut client = Reaper(
+            Command::new(env!("CARGO_BIN_EXE_gtk-cursor-navigator"))
+                .args(["--server", &server_addr, "--focus-query", "none"])
+                .arg("--record-pointer")
+                .arg(&record_file)
+                .env("XDG_RUNTIME_DIR", &runtime_dir)
+                .env("WAYLAND_DISPLAY", &display)
+                .env("GDK_BACKEND", "wayland")
+                .env("GSK_RENDERER", "cairo")
+                .env_remove("DISPLAY")
+                .stdout(Stdio::null())
+                .spawn()
+                .expect("Failed to start the client"),
+        );
+        thread::sleep(Duration::from_secs(1));
+        Command::new("wtype")
+            .arg(TOKEN)
+            .env("XDG_RUNTIME_DIR", &runtime_dir)
+            .env("WAYLAND_DISPLAY", &display)
+            .status()
+            .expect("Failed to run wtype");
+
+        let deadline = Instant::now() + Duration::from_secs(3);
+        while Instant::now() < deadline {
+            if let Some(status) = client.0.try_wait().unwrap() {
+                assert!(status.success(), "The client failed: {}", status);
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        if let Ok(recorded) = fs::read_to_string(&record_file) {
+            moved = recorded
+                .lines()
+                .map(|line| serde_json::from_str::<PointerAction>(line).unwrap())
+                .find(|action| matches!(action, PointerAction::MoveTo { .. }));
+            if moved.is_some() {
+                break;
+            }
+        }
+    }
+
+    let _ = fs::remove_dir_all(&dir);
+    assert_eq!(
+        moved,
+        Some(PointerAction::MoveTo { x: CENTER.0, y: CENTER.1 }),
+        "The pointer wasn't moved to the center of cell {}",
+        TOKEN
+    );
+}
@@ -0,0 +1,99 @@
+//! Label font sizes must keep every label inside its cell, so the homogeneous grid
+//! never outgrows the output, for common resolutions and grid sizes.
+
+use gtk_cursor_navigator::conf::{ConfTheme, FontSizing};
+use gtk_cursor_navigator::geometry::{label_font_size, max_fitting_font_size, min_cell_size};
+
+fn theme(font_sizing: FontSizing) -> ConfTheme {
+    ConfTheme {
+        font_sizing,
+        font_size: 32,
+        ..ConfTheme::default()
+    }
+}
+
+/// A width and a height, of the output or in rows and columns.
+type Size = (u32, u32);
+
+/// Font sizes of two-letter labels, with 1 pixel lines and the default bounds of 8 to
+/// 48 pixels: (output, rows × columns, fixed, fit, fraction of 0.5).
+const EXPECTED: &[(Size, Size, u32, u32, u32)] = &[
+    // 640 × 432 cells: everything is bounded by the settings.
+    ((3840, 2160), (5, 6), 32, 48, 48),
+    // 96 × 108 cells fit up to 60 pixels.
+    ((1920, 1080), (10, 20), 32, 48, 48),
+    // 32 × 36 cells fit up to 17 pixels.
+    ((1280, 720), (20, 40), 17, 17, 17),
+    // 21 × 18 cells fit up to 10 pixels.
+    ((1280, 720), (40, 60), 10, 10, 9),
+    // 16 × 12 cells fit up to 6 pixels, below the minimum.
+    ((1280, 720), (60, 80), 6, 6, 6),
+];
+
+#[test]
+fn sizes_follow_the_mode_and_the_cells() {
+    for &((width, height), (rows, columns), fixed, fit, fraction) in EXPECTED {
+        let (cell_width, cell_height) = min_cell_size(width, height, rows, columns);
+        let modes = [(FontSizing::Fixed, fixed), (FontSizing::Fit, fit), (FontSizing::Fraction, fraction)];
+        for (mode, expected) in modes {
+            assert_eq!(
+                label_font_size(cell_width, cell_height, 2, &theme(mode)),
+                expected,
+                "{width}x{height}, {rows}x{columns}, {mode:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn fitting_size_leaves_room_for_the_lines() {
+    // 0.75 em per glyph and 1.3 em per line, inside a border of `line_pixel` and the
+    // 2 pixel padding of the pill on either side.
+    assert_eq!(max_fitting_font_size(96, 108, 2, 1), 60);
+    assert_eq!(max_fitting_font_size(96, 108, 2, 4), 56);
+    assert_eq!(max_fitting_font_size(200, 40, 3, 1), 29);
+    assert_eq!(max_fitting_font_size(8, 4, 2, 1), 1);
+}
+
+#[test]
+fn nothing_fits_into_cells_smaller_than_a_pixel_font() {
+    assert_eq!(max_fitting_font_size(3, 3, 2, 1), 0);
+    assert_eq!(max_fitting_font_size(2, 40, 2, 1), 0);
+    // Wide enough for a 1 pixel font, but not with the pill padding.
+    assert_eq!(max_fitting_font_size(7, 40, 2, 1), 0);
+    for mode in [FontSizing::Fixed, FontSizing::Fit, FontSizing::Fraction] {
+        assert_eq!(label_font_size(3, 3, 2, &theme(mode)), 0, "{mode:?}");
+    }
+}
+
+#[test]
+fn fit_and_fraction_respect_bounds_when_cells_allow() {
+    let mut theme = theme(FontSizing::Fit);
+    theme.font_size_min = 10;
+    theme.font_size_max = 20;
+    // Roomy cells: clamped to the maximum.
+    assert_eq!(label_font_size(400, 300, 2, &theme), 20);
+    // Cells fitting 14px: used as is.
+    assert_eq!(label_font_size(200, 21, 2, &theme), 14);
+
+    theme.font_sizing = FontSizing::Fraction;
+    theme.font_size_fraction = 0.5;
+    assert_eq!(label_font_size(400, 30, 2, &theme), 15);
+    assert_eq!(label_font_size(400, 100, 2, &theme), 20);
+}
+
+#[test]
+fn fitting_wins_over_minimum_in_tiny_cells() {
+    let mut theme = theme(FontSizing::Fit);
+    theme.font_size_min = 12;
+    // 10 × 9 cells fit up to 2 pixels.
+    let (cell_width, cell_height) = min_cell_size(1280, 720, 80, 120);
+    assert_eq!(label_font_size(cell_width, cell_height, 2, &theme), 2);
+}
+
+#[test]
+fn fixed_size_is_kept_when_it_fits() {
+    let theme = theme(FontSizing::Fixed);
+    let (cell_width, cell_height) = min_cell_size(2560, 1440, 5, 6);
+    assert_eq!(label_font_size(cell_width, cell_height, 2, &theme), 32);
+}
@@ -0,0 +1,224 @@
+//! Properties of the overlay geometry over many configurations: the cells tile the grid
+//! area without gaps or overlaps, every center lies inside its cell, and the point-to-cell
+//! lookup agrees with the cells. Configurations are drawn from a fixed-seed generator so
+//! failures are reproducible.
+
+use gtk_cursor_navigator::conf::{ConfGrid, ConfRegion, Length, ReservedNotCovered};
+use gtk_cursor_navigator::geometry::{region_on_output, OverlayGeometry, Rect};
+
+/// xorshift64*, enough to spread the configurations.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn range(&mut self, low: u32, high: u32) -> u32 {
+        low + (self.next() % (high - low + 1) as u64) as u32
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.next() as usize % items.len()]
+    }
+}
+
+fn random_lengths(rng: &mut Rng, count: u32) -> Vec<Length> {
+    (0..count)
+        .map(|_| match rng.range(0, 2) {
+            0 => Length::Pixels(rng.range(5, 60)),
+            _ => Length::Fraction(rng.range(1, 8) as f64 / 4.0),
+        })
+        .collect()
+}
+
+fn random_grid(rng: &mut Rng) -> ConfGrid {
+    let mut grid = ConfGrid {
+        rows: rng.range(1, 60) as u8,
+        columns: rng.range(1, 80) as u8,
+        ..ConfGrid::default()
+    };
+    match rng.range(0, 3) {
+        0 => {}
+        1 => {
+            let rows = rng.range(1, 12);
+            grid.row_sizes = random_lengths(rng, rows);
+            // At least one fraction, so the tracks fill the area.
+            grid.row_sizes.push(Length::Fraction(1.0));
+        }
+        2 => {
+            let columns = rng.range(1, 12);
+            grid.column_sizes = random_lengths(rng, columns);
+            grid.column_sizes.push(Length::Fraction(1.0));
+        }
+        _ => {
+            // A band on top and the rest below, the common toolbar layout.
+            let band = rng.range(10, 80);
+            grid.regions = vec![
+                ConfRegion {
+                    height: Length::Pixels(band),
+                    rows: 1,
+                    columns: rng.range(1, 40) as u8,
+                    ..ConfRegion::default()
+                },
+                ConfRegion {
+                    y: Length::Pixels(band),
+                    rows: rng.range(1, 30) as u8,
+                    columns: rng.range(1, 40) as u8,
+                    ..ConfRegion::default()
+                },
+            ];
+        }
+    }
+    grid
+}
+
+fn random_geometry(rng: &mut Rng) -> OverlayGeometry {
+    let (width, height) = rng.pick(&[(1280, 720), (1366, 768), (1920, 1080), (2560, 1440), (1080, 1920)]);
+    let output = Rect::new(rng.pick(&[0.0, 1920.0, -1280.0]), 0.0, width as f64, height as f64);
+    let scale = rng.pick(&[1.0, 2.0, 3.0]);
+    let reserved = ReservedNotCovered {
+        top: rng.pick(&[0, 0, 30]),
+        bottom: rng.pick(&[0, 24]),
+        left: rng.pick(&[0, 48]),
+        right: rng.pick(&[0, 10]),
+    };
+    let scope = match rng.range(0, 2) {
+        0 => Some(Rect::new(
+            rng.range(0, width / 2) as f64,
+            rng.range(0, height / 2) as f64,
+            rng.range(200, width / 2) as f64,
+            rng.range(200, height / 2) as f64,
+        )),
+        _ => None,
+    };
+    let header_strip = rng.pick(&[0.0, 0.0, 32.0]);
+    OverlayGeometry::new(output, scale, &reserved, scope, &random_grid(rng), header_strip)
+}
+
+fn for_each_geometry(check: impl Fn(&OverlayGeometry)) {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..300 {
+        check(&random_geometry(&mut rng));
+    }
+}
+
+/// The grid area in global layout coordinates.
+fn global_grid_area(geometry: &OverlayGeometry) -> Rect {
+    let (x, y) = geometry.surface_origin();
+    geometry.grid_area.translate(x, y)
+}
+
+#[test]
+fn cells_tile_the_grid_area() {
+    for_each_geometry(|geometry| {
+        let area = global_grid_area(geometry);
+        let cells = geometry.cells();
+        let covered: f64 = cells.iter().map(|c| c.rect.width * c.rect.height).sum();
+        assert!(
+            (covered - area.width * area.height).abs() < 1e-6,
+            "{:?}: cells cover {} of {}",
+            geometry,
+            covered,
+            area.width * area.height
+        );
+        for cell in &cells {
+            let inside = cell.rect.intersect(&area);
+            assert!(
+                (inside.width * inside.height - cell.rect.width * cell.rect.height).abs() < 1e-6,
+                "{:?} sticks out of {:?}",
+                cell,
+                area
+            );
+        }
+        // With the areas adding up and every cell inside, no two cells may overlap; check
+        // neighbours anyway, so an overlap is reported where it happens.
+        for pair in cells.windows(2) {
+            let shared = pair[0].rect.intersect(&pair[1].rect);
+            assert!(shared.width * shared.height < 1e-6, "{:?} overlap", pair);
+        }
+    });
+}
+
+#[test]
+fn centers_lie_inside_their_cells() {
+    for_each_geometry(|geometry| {
+        for (index, cell) in geometry.cells().iter().enumerate() {
+            if cell.rect.width < 1.0 || cell.rect.height < 1.0 {
+                continue;
+            }
+            let (x, y) = cell.center;
+            assert!(
+                cell.rect.contains(x as f64, y as f64),
+                "center {:?} of cell {} outside {:?}",
+                cell.center,
+                index,
+                cell.rect
+            );
+        }
+    });
+}
+
+#[test]
+fn lookup_agrees_with_cells() {
+    for_each_geometry(|geometry| {
+        let cells = geometry.cells();
+        for (index, cell) in cells.iter().enumerate() {
+            if cell.rect.width == 0.0 || cell.rect.height == 0.0 {
+                continue;
+            }
+            let (x, y) = cell.rect.center();
+            assert_eq!(geometry.cell_at(x, y), Some(index), "{:?}", cell);
+            // The top-left corner belongs to the cell, the edges past it to the neighbours.
+            assert_eq!(geometry.cell_at(cell.rect.x, cell.rect.y), Some(index));
+        }
+        let area = global_grid_area(geometry);
+        assert_eq!(geometry.cell_at(area.x - 1.0, area.y), None);
+        assert_eq!(geometry.cell_at(area.x + area.width, area.y + area.height), None);
+    });
+}
+
+#[test]
+fn reserved_edges_and_scope_place_the_surface() {
+    let grid = ConfGrid { rows: 2, columns: 2, ..ConfGrid::default() };
+    let output = Rect::new(1920.0, 0.0, 2560.0, 1440.0);
+    let reserved = ReservedNotCovered { top: 30, bottom: 0, left: 0, right: 0 };
+
+    let full = OverlayGeometry::new(output, 1.0, &reserved, None, &grid, 0.0);
+    assert_eq!(full.surface, Rect::new(0.0, 30.0, 2560.0, 1410.0));
+    assert_eq!(full.cells()[0].center, (1920 + 640, 30 + 353));
+
+    // A scope reaching into the top bar is clamped below it.
+    let scope = Rect::new(100.0, 0.0, 400.0, 300.0);
+    let scoped = OverlayGeometry::new(output, 2.0, &reserved, Some(scope), &grid, 0.0);
+    assert_eq!(scoped.surface, Rect::new(100.0, 30.0, 400.0, 270.0));
+    assert_eq!(scoped.cells()[3].rect, Rect::new(2220.0, 165.0, 200.0, 135.0));
+}
+
+#[test]
+fn nudged_pointer_stays_on_the_output() {
+    let output = Rect::new(1920.0, 0.0, 2560.0, 1440.0);
+    assert_eq!(output.clamp_pixel(2000, 700), (2000, 700));
+    assert_eq!(output.clamp_pixel(1900, -10), (1920, 0));
+    assert_eq!(output.clamp_pixel(5000, 1440), (4479, 1439));
+}
+
+#[test]
+fn regions_are_placed_on_the_output_showing_them() {
+    let grid = ConfGrid { rows: 2, columns: 2, ..ConfGrid::default() };
+    let reserved = ReservedNotCovered { top: 0, bottom: 0, left: 0, right: 0 };
+    let outputs = [Rect::new(0.0, 0.0, 1920.0, 1080.0), Rect::new(1920.0, 200.0, 2560.0, 1440.0)];
+
+    // As slurp prints it, in global layout coordinates, on the second output.
+    let region = Rect::new(2020.0, 300.0, 400.0, 300.0);
+    let (index, scope) = region_on_output(region, &outputs).unwrap();
+    assert_eq!((index, scope), (1, Rect::new(100.0, 100.0, 400.0, 300.0)));
+    let geometry = OverlayGeometry::new(outputs[index], 1.0, &reserved, Some(scope), &grid, 0.0);
+    assert_eq!(geometry.cells()[0].rect, Rect::new(2020.0, 300.0, 200.0, 150.0));
+    assert_eq!(geometry.cells()[3].center, (2320, 525));
+
+    assert_eq!(region_on_output(Rect::new(-500.0, 0.0, 100.0, 100.0), &outputs), None);
+}
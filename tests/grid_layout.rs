@@ -0,0 +1,117 @@
+//! Non-uniform grids: sized tracks and regions must tile their area without gaps, and
+//! the hit test must agree with the cells handed to the renderers.
+
+use gtk_cursor_navigator::conf::{ConfGrid, ConfRegion, Length};
+use gtk_cursor_navigator::geometry::{
+    grid_cells, parse_region, sized_track_offsets, GridLayout, Rect,
+};
+
+#[test]
+fn fractions_share_what_pixels_leave() {
+    let sizes = [Length::Pixels(40), Length::Fraction(1.0), Length::Fraction(2.0)];
+    assert_eq!(sized_track_offsets(1000, &sizes), vec![0, 40, 360, 1000]);
+    // Rounding the running total keeps the last boundary on the edge.
+    let thirds = [Length::Fraction(1.0); 3];
+    assert_eq!(*sized_track_offsets(100, &thirds).last().unwrap(), 100);
+    // Pixel tracks past the edge are cut.
+    assert_eq!(sized_track_offsets(50, &[Length::Pixels(40), Length::Pixels(40)]), vec![0, 40, 50]);
+}
+
+#[test]
+fn uniform_layout_matches_grid_cells() {
+    let grid = ConfGrid::default();
+    let layout = GridLayout::new(1920, 1080, &grid);
+    let rows = grid.rows as u32;
+    let columns = grid.columns as u32;
+    assert_eq!(layout.cells(), grid_cells(1920, 1080, rows, columns));
+    assert_eq!(layout.cells().len(), grid.cell_count());
+}
+
+#[test]
+fn regions_number_cells_in_order_and_hit_test_them() {
+    let grid = ConfGrid {
+        regions: vec![
+            ConfRegion {
+                name: "menu".to_string(),
+                height: Length::Pixels(30),
+                rows: 1,
+                columns: 20,
+                ..ConfRegion::default()
+            },
+            ConfRegion {
+                name: "main".to_string(),
+                y: Length::Pixels(30),
+                rows: 5,
+                column_sizes: vec![Length::Fraction(1.0), Length::Fraction(3.0)],
+                ..ConfRegion::default()
+            },
+        ],
+        ..ConfGrid::default()
+    };
+    let layout = GridLayout::new(800, 630, &grid);
+    let cells = layout.cells();
+    assert_eq!(cells.len(), 20 + 5 * 2);
+    assert_eq!(grid.cell_count(), cells.len());
+    assert_eq!(layout.min_cell_size(), (40, 30));
+
+    for (index, cell) in cells.iter().enumerate() {
+        let (x, y) = cell.center();
+        assert_eq!(layout.cell_at(x, y), Some(index));
+    }
+    // The main region starts right below the menu band and fills the rest.
+    assert_eq!(cells[20].y, 30.0);
+    assert_eq!(cells[20].width, 200.0);
+    assert_eq!(cells.last().unwrap().y + cells.last().unwrap().height, 630.0);
+    assert_eq!(layout.cell_at(800.0, 0.0), None);
+}
+
+#[test]
+fn fractional_region_sizes_are_fractions_of_the_overlay() {
+    let grid = ConfGrid {
+        regions: vec![
+            ConfRegion {
+                x: Length::Fraction(0.5),
+                width: Length::Fraction(0.5),
+                ..ConfRegion::default()
+            },
+            // Past the right edge: cut there.
+            ConfRegion {
+                x: Length::Fraction(0.75),
+                width: Length::Fraction(0.5),
+                height: Length::Fraction(0.25),
+                ..ConfRegion::default()
+            },
+        ],
+        ..ConfGrid::default()
+    };
+    let layout = GridLayout::new(800, 600, &grid);
+    assert_eq!(layout.regions[0].bounds(), Rect::new(400.0, 0.0, 400.0, 600.0));
+    assert_eq!(layout.regions[1].bounds(), Rect::new(600.0, 0.0, 200.0, 150.0));
+}
+
+#[test]
+fn lengths_parse_as_pixels_or_fractions() {
+    let grid: ConfGrid = toml::from_str("row_sizes = [40, 0.5, 1.5]").unwrap();
+    assert_eq!(
+        grid.row_sizes,
+        vec![Length::Pixels(40), Length::Fraction(0.5), Length::Fraction(1.5)]
+    );
+}
+
+#[test]
+fn regions_parse_in_slurp_format() {
+    let expected = Rect::new(10.0, 20.0, 300.0, 200.0);
+    assert_eq!(parse_region("10,20 300x200\n"), Ok(expected));
+    assert_eq!(parse_region("10,20,300x200"), Ok(expected));
+    assert!(parse_region("10,20 300").is_err());
+    assert!(parse_region("10 300x200").is_err());
+    assert!(parse_region("10,20 0x200").is_err());
+}
+
+#[test]
+fn selections_format_like_slurp() {
+    let cell = Rect::new(100.0, 50.0, 41.0, 20.0);
+    assert_eq!(cell.format("%X,%Y"), "121,60");
+    assert_eq!(cell.format("%x,%y %wx%h"), "100,50 41x20");
+    assert_eq!(cell.format("100%% %q"), "100% %q");
+}
@@ -0,0 +1,83 @@
+//! Runs the AT-SPI element discovery against `examples/hint-target-app.rs` on a private
+//! accessibility bus, so the result doesn't depend on what runs on the desktop.
+//!
+//! Needs `dbus-daemon`, `at-spi2-registryd` and a display for GTK, hence ignored by default:
+//!     cargo test --test hint -- --ignored
+
+mod common;
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::Reaper;
+use gtk_cursor_navigator::hint;
+
+const REGISTRYD_PATHS: &[&str] = &[
+    "/usr/libexec/at-spi2-registryd",
+    "/usr/lib/at-spi2-registryd",
+    "/usr/lib/at-spi2-core/at-spi2-registryd",
+    "/usr/lib/x86_64-linux-gnu/at-spi2-registryd",
+];
+
+fn example_path(name: &str) -> PathBuf {
+    // Test binaries live in target/<profile>/deps, examples in target/<profile>/examples.
+    let exe = std::env::current_exe().unwrap();
+    exe.parent().unwrap().parent().unwrap().join("examples").join(name)
+}
+
+#[test]
+#[ignore = "needs dbus-daemon, at-spi2-registryd and a display"]
+fn finds_buttons_of_test_app_on_private_bus() {
+    let mut daemon = Command::new("dbus-daemon")
+        .args(["--session", "--nofork", "--print-address=1"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to start dbus-daemon");
+    let mut address = String::new();
+    BufReader::new(daemon.stdout.take().unwrap())
+        .read_line(&mut address)
+        .unwrap();
+    let address = address.trim().to_string();
+    let _daemon = Reaper(daemon);
+
+    let registryd = REGISTRYD_PATHS
+        .iter()
+        .find(|p| Path::new(p).exists())
+        .expect("at-spi2-registryd not found");
+    let _registry = Reaper(
+        Command::new(registryd)
+            .env("AT_SPI_BUS_ADDRESS", &address)
+            .spawn()
+            .unwrap(),
+    );
+    let _app = Reaper(
+        Command::new(example_path("hint-target-app"))
+            .env("AT_SPI_BUS_ADDRESS", &address)
+            .env("GTK_A11Y", "atspi")
+            .spawn()
+            .expect("Failed to start hint-target-app, build it with `cargo build --examples`"),
+    );
+
+    let conn = hint::connect(&address).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let targets = loop {
+        let targets = hint::collect_targets(&conn, Some("gcn-hint-target")).unwrap_or_default();
+        if targets.len() >= 3 || Instant::now() > deadline {
+            break targets;
+        }
+        thread::sleep(Duration::from_millis(200));
+    };
+
+    let names: Vec<&str> = targets.iter().map(|t| t.name.as_str()).collect();
+    for expected in ["Alpha", "Beta", "Gamma"] {
+        assert!(names.contains(&expected), "{expected} missing from {names:?}");
+    }
+    for target in &targets {
+        let (x, y) = target.center();
+        assert!(x >= target.x && x < target.x + target.width);
+        assert!(y >= target.y && y < target.y + target.height);
+    }
+}
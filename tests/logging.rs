@@ -0,0 +1,61 @@
+//! Checks the log records written to file: JSON fields, timestamps and rotation.
+
+mod common;
+
+use std::fs;
+use std::time::{Duration, UNIX_EPOCH};
+
+use common::scratch_dir;
+use gtk_cursor_navigator::conf::ConfLog;
+use gtk_cursor_navigator::logging::{self, RotatingFile};
+use log::LevelFilter;
+
+#[test]
+fn json_records_keep_fields() {
+    let dir = scratch_dir("log-json");
+    let conf = ConfLog {
+        level: "warn".to_string(),
+        json: true,
+        file: true,
+        dir: dir.to_str().unwrap().to_string(),
+        ..ConfLog::default()
+    };
+    logging::init("gcn-test", &conf, Some(LevelFilter::Info));
+    log::debug!("Not logged at the info level");
+    let (x, y) = (640, 360);
+    log::info!(index = 7usize, x = x, y = y, cell:? = (600.0, 300.0); "Moving the pointer");
+
+    let contents = fs::read_to_string(dir.join("gcn-test.log")).unwrap();
+    let lines: Vec<&str> = contents.lines().collect();
+    assert_eq!(lines.len(), 1);
+    let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+    assert_eq!(record["level"], "INFO");
+    assert_eq!(record["message"], "Moving the pointer");
+    assert_eq!(record["fields"]["index"], 7);
+    assert_eq!(record["fields"]["x"], 640);
+    assert_eq!(record["fields"]["y"], 360);
+    assert_eq!(record["fields"]["cell"], "(600.0, 300.0)");
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn timestamps_are_utc() {
+    assert_eq!(logging::timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+    let leap_day = UNIX_EPOCH + Duration::from_millis(951_868_799_250);
+    assert_eq!(logging::timestamp(leap_day), "2000-02-29T23:59:59.250Z");
+}
+
+#[test]
+fn files_rotate_by_size() {
+    let dir = scratch_dir("log-rotate");
+    let path = dir.join("app.log");
+    let mut file = RotatingFile::open(&path, 20, 2).unwrap();
+    for line in ["first line\n", "second line\n", "third line\n", "fourth line\n"] {
+        file.write_line(line).unwrap();
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap(), "fourth line\n");
+    assert_eq!(fs::read_to_string(file.rotated_path(1)).unwrap(), "third line\n");
+    assert_eq!(fs::read_to_string(file.rotated_path(2)).unwrap(), "second line\n");
+    assert!(!file.rotated_path(3).exists());
+    let _ = fs::remove_dir_all(&dir);
+}
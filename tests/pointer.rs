@@ -0,0 +1,49 @@
+//! Wheel events of the ydotool backend, read from a socket standing in for its daemon.
+
+mod common;
+
+use std::fs;
+use std::os::unix::net::UnixDatagram;
+
+use common::scratch_dir;
+use gtk_cursor_navigator::pointer::{PointerBackend, ScrollAmount, ScrollAxis, Ydotool};
+
+/// The `(type, code, value)` of every event the daemon received; the timestamp leads
+/// the event.
+fn received(daemon: &UnixDatagram) -> Vec<(u16, u16, i32)> {
+    let mut events = Vec::new();
+    let mut buffer = [0; 64];
+    while let Ok(len) = daemon.recv(&mut buffer) {
+        let event = &buffer[len - 8..len];
+        events.push((
+            u16::from_ne_bytes([event[0], event[1]]),
+            u16::from_ne_bytes([event[2], event[3]]),
+            i32::from_ne_bytes([event[4], event[5], event[6], event[7]]),
+        ));
+    }
+    events
+}
+
+#[test]
+fn high_resolution_amounts_scroll_on_every_press() {
+    let dir = scratch_dir("ydotool-socket");
+    let socket = dir.join("socket");
+    let daemon = UnixDatagram::bind(&socket).unwrap();
+    daemon.set_nonblocking(true).unwrap();
+    let backend = Ydotool::new(socket);
+
+    // Three presses of 40 units down: a whole detent is added on the third one, and the
+    // kernel counts vertical motion upwards.
+    for _ in 0..3 {
+        backend.scroll(ScrollAxis::Vertical, ScrollAmount::HighResolution(40)).unwrap();
+    }
+    let hi_res = (0x02, 0x0b, -40);
+    let report = (0, 0, 0);
+    let detent = (0x02, 0x08, -1);
+    assert_eq!(received(&daemon), [hi_res, report, hi_res, report, hi_res, detent, report]);
+
+    // Discrete amounts send the detents along with their units, to the right here.
+    backend.scroll(ScrollAxis::Horizontal, ScrollAmount::Discrete(2)).unwrap();
+    assert_eq!(received(&daemon), [(0x02, 0x0c, 240), (0x02, 0x06, 2), report]);
+    let _ = fs::remove_dir_all(&dir);
+}
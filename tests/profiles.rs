@@ -0,0 +1,146 @@
+//! Profiles: finding the focused window in compositor replies, matching it against the
+//! profile patterns, and applying the profile overrides to any section.
+
+use gtk_cursor_navigator::conf::Conf;
+use gtk_cursor_navigator::focus::{
+    parse_hyprland_window, parse_lswt, parse_sway_tree, FocusedWindow,
+};
+use gtk_cursor_navigator::generate_grid_tokens;
+use gtk_cursor_navigator::geometry::Rect;
+use serde_json::json;
+
+const CONFIG: &str = r#"
+[grid]
+rows = 10
+columns = 20
+
+[profiles.browser]
+app_id = "firefox"
+grid = { rows = 4, columns = 6, key_left = "A S D F", key_right = "J K L ;" }
+theme = { font_size = 24 }
+
+[profiles.terminal]
+app_id = "foot"
+title = "vim"
+"#;
+
+fn window(app_id: &str, title: &str) -> FocusedWindow {
+    FocusedWindow { app_id: app_id.to_string(), title: title.to_string(), rect: None }
+}
+
+/// The name of the first profile, in name order, whose patterns match `window`.
+fn matching_profile<'a>(config: &'a Conf, window: &FocusedWindow) -> Option<&'a str> {
+    config
+        .profiles
+        .iter()
+        .find(|(_, profile)| profile.matcher().unwrap().matches(window))
+        .map(|(name, _)| name.as_str())
+}
+
+#[test]
+fn profiles_match_every_given_pattern() {
+    let config: Conf = toml::from_str(CONFIG).unwrap();
+    assert_eq!(matching_profile(&config, &window("firefox", "Mozilla")), Some("browser"));
+    assert_eq!(matching_profile(&config, &window("foot", "vim notes.txt")), Some("terminal"));
+    assert_eq!(matching_profile(&config, &window("foot", "htop")), None);
+    assert_eq!(matching_profile(&config, &window("gimp", "")), None);
+}
+
+#[test]
+fn focused_window_matters_only_with_patterns() {
+    let config: Conf = toml::from_str(CONFIG).unwrap();
+    assert!(config.has_window_patterns());
+    let by_name: Conf = toml::from_str("[profiles.presentation]\ngrid = { rows = 3 }\n").unwrap();
+    assert!(!by_name.has_window_patterns());
+    assert!(!Conf::default().has_window_patterns());
+}
+
+#[test]
+fn invalid_patterns_are_refused_when_compiled() {
+    let config: Conf = toml::from_str("[profiles.broken]\napp_id = \"(firefox\"\n").unwrap();
+    assert!(config.profiles["broken"].matcher().is_err());
+}
+
+#[test]
+fn profiles_override_grid_theme_and_tokens() {
+    let config: Conf = toml::from_str(CONFIG).unwrap();
+    let browser = config.with_profile("browser").unwrap();
+    assert_eq!((browser.grid.rows, browser.grid.columns), (4, 6));
+    assert_eq!(browser.theme.font_size, 24);
+    // Settings the profile leaves alone come from the main configuration.
+    assert_eq!(browser.theme.line_pixel, config.theme.line_pixel);
+    assert!(browser.profiles.is_empty());
+
+    let tokens = generate_grid_tokens(&browser.grid);
+    assert_eq!(tokens.len(), 24);
+    assert!(tokens.iter().all(|t| t.chars().all(|c| "ASDFJKL;".contains(c))));
+    assert!(config.with_profile("missing").is_err());
+}
+
+#[test]
+fn profiles_override_any_section() {
+    let config: Conf = toml::from_str(
+        r#"
+        [reserved]
+        top = 30
+
+        [profiles.presentation]
+        reserved = { top = 0 }
+        nudge = { enabled = true }
+
+        [profiles.presentation.grid]
+        rows = 3
+        regions = [{ name = "left", width = 0.5 }]
+        "#,
+    )
+    .unwrap();
+    let presentation = config.with_profile("presentation").unwrap();
+    assert_eq!(presentation.reserved.top, 0);
+    assert!(presentation.nudge.enabled);
+    assert_eq!(presentation.grid.rows, 3);
+    assert_eq!(presentation.grid.regions.len(), 1);
+    assert_eq!(presentation.grid.columns, config.grid.columns);
+}
+
+#[test]
+fn focused_window_is_found_in_compositor_replies() {
+    let tree = json!({
+        "type": "root", "focused": false,
+        "nodes": [{
+            "type": "output", "focused": false,
+            "rect": { "x": 1920, "y": 0, "width": 2560, "height": 1440 },
+            "nodes": [{
+                "type": "workspace", "focused": false,
+                "nodes": [{ "type": "con", "focused": false, "app_id": "foot", "name": "shell" }],
+                "floating_nodes": [{
+                    "type": "floating_con", "focused": true, "app_id": null,
+                    "name": "Xterm", "window_properties": { "class": "XTerm" },
+                    "rect": { "x": 2020, "y": 50, "width": 800, "height": 600 }
+                }]
+            }]
+        }]
+    });
+    let xterm = FocusedWindow {
+        rect: Some(Rect::new(100.0, 50.0, 800.0, 600.0)),
+        ..window("XTerm", "Xterm")
+    };
+    assert_eq!(parse_sway_tree(&tree).unwrap(), Some(xterm));
+
+    let monitors = json!([{ "id": 0, "x": 0, "y": 0 }, { "id": 1, "x": 1920, "y": 0 }]);
+    let active = json!({
+        "class": "firefox", "title": "Mozilla Firefox",
+        "at": [1930, 40], "size": [1000, 700], "monitor": 1
+    });
+    let firefox = FocusedWindow {
+        rect: Some(Rect::new(10.0, 40.0, 1000.0, 700.0)),
+        ..window("firefox", "Mozilla Firefox")
+    };
+    assert_eq!(parse_hyprland_window(&active, &monitors).unwrap(), Some(firefox));
+    assert_eq!(parse_hyprland_window(&json!({}), &monitors).unwrap(), None);
+
+    let list = json!({ "toplevels": [
+        { "title": "a", "app-id": "foot", "activated": false },
+        { "title": "b", "app-id": "gimp", "activated": true }
+    ]});
+    assert_eq!(parse_lswt(&list).unwrap(), Some(window("gimp", "b")));
+}
@@ -0,0 +1,68 @@
+//! The JSON Schema printed by `gtk-cursor-navigator-server schema`.
+
+use gtk_cursor_navigator::schema::conf_schema;
+use serde_json::Value;
+
+/// The definition a property refers to, or the property itself.
+fn resolve<'a>(schema: &'a Value, property: &'a Value) -> &'a Value {
+    match property["$ref"].as_str().and_then(|path| path.strip_prefix("#/$defs/")) {
+        Some(name) => &schema["$defs"][name],
+        None => property,
+    }
+}
+
+#[test]
+fn sections_have_types_defaults_and_descriptions() {
+    let schema = conf_schema();
+    for section in ["grid", "theme", "reserved", "shortcut"] {
+        let property = &schema["properties"][section];
+        assert!(property["default"].is_object(), "{} has no default", section);
+        let definition = resolve(&schema, property);
+        let fields = definition["properties"].as_object().unwrap();
+        assert!(!fields.is_empty(), "{} has no fields", section);
+        for (name, field) in fields {
+            assert!(field["description"].is_string(), "{}.{} has no description", section, name);
+            assert!(!field["default"].is_null(), "{}.{} has no default", section, name);
+        }
+    }
+}
+
+#[test]
+fn ranges_and_enum_values_follow_the_structs() {
+    let schema = conf_schema();
+    let grid = &schema["$defs"]["ConfGrid"]["properties"];
+    assert_eq!(grid["rows"]["minimum"], 1);
+    assert_eq!(grid["rows"]["maximum"], 255);
+    assert_eq!(grid["rows"]["default"], 10);
+    assert_eq!(resolve(&schema, &grid["label_mode"])["enum"], serde_json::json!(["tokens", "coordinates"]));
+
+    let theme = &schema["$defs"]["ConfTheme"]["properties"];
+    assert_eq!(theme["opacity"]["maximum"], 1.0);
+    assert_eq!(theme["background_opacity"]["default"], 0.8);
+    assert!(theme["font_weight"]["enum"].is_null());
+    assert!(theme["font_weight"]["examples"].as_array().unwrap().contains(&"bold".into()));
+
+    let modifier = &schema["$defs"]["ConfNudge"]["properties"]["large_modifier"];
+    assert!(modifier["enum"].is_null());
+    assert!(modifier["pattern"].as_str().unwrap().contains("[Ss][Hh][Ii][Ff][Tt]"));
+
+    let finish = &schema["$defs"]["ConfShortcut"]["properties"]["finish"];
+    assert_eq!(finish["type"], "array");
+    assert!(finish["items"]["anyOf"][0]["enum"].as_array().unwrap().contains(&"Escape".into()));
+}
+
+#[test]
+fn example_config_uses_only_known_settings() {
+    let schema = conf_schema();
+    let example: toml::Value =
+        toml::from_str(&std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/config.toml")).unwrap())
+            .unwrap();
+    for (section, settings) in example.as_table().unwrap() {
+        let property = &schema["properties"][section];
+        assert!(property.is_object(), "Unknown section {}", section);
+        let definition = resolve(&schema, property);
+        for name in settings.as_table().unwrap().keys() {
+            assert!(definition["properties"][name].is_object(), "Unknown setting {}.{}", section, name);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Starts the server on a scratch configuration and checks what the status and reload
+//! requests report, switching the active profile, and that only one server runs at a time.
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use common::{free_port, scratch_dir, wait_for_listener, Reaper};
+use gtk_cursor_navigator::SharedData;
+use gtk_cursor_navigator::protocol::{Request, ServerStatus, send_request};
+
+fn status(addr: &str, request: &Request) -> ServerStatus {
+    serde_json::from_str(&send_request(addr, request).unwrap()).unwrap()
+}
+
+/// Starts a server with its runtime directory, and so its pid file, in `dir`.
+fn server(dir: &Path, port: u16, extra: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_gtk-cursor-navigator-server"));
+    command
+        .args(["--config", dir.join("config.toml").to_str().unwrap(), "--port", &port.to_string()])
+        .args(extra)
+        .env("XDG_RUNTIME_DIR", dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+#[test]
+fn status_reports_tokens_clients_and_reloads() {
+    let dir = scratch_dir("status");
+    let config_file = dir.join("config.toml");
+    fs::write(&config_file, "[grid]\nrows = 2\ncolumns = 3\n").unwrap();
+
+    let port = free_port();
+    let addr = format!("127.0.0.1:{}", port);
+    let _server = Reaper(server(&dir, port, &[]).spawn().expect("Failed to start the server"));
+    wait_for_listener(&addr);
+
+    let before = status(&addr, &Request::Status);
+    assert_eq!(before.token_count, 6);
+    assert_eq!(before.reloads, 0);
+    assert_eq!(before.last_error, None);
+    assert!(!before.config_is_stale());
+
+    fs::write(&config_file, "[grid]\nrows = 4\ncolumns = 3\n").unwrap();
+    let after = status(&addr, &Request::Reload);
+    assert_eq!(after.token_count, 12);
+    assert_eq!(after.reloads, 1);
+    assert!(after.clients_served > before.clients_served);
+
+    fs::write(&config_file, "[grid]\nrows = \"many\"\n").unwrap();
+    let failed = status(&addr, &Request::Reload);
+    assert_eq!(failed.token_count, 12);
+    assert_eq!(failed.reloads, 1);
+    assert!(failed.last_error.is_some());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn status_notices_changed_included_files() {
+    let dir = scratch_dir("status-include");
+    fs::write(dir.join("config.toml"), "include = [\"grid.toml\"]\n").unwrap();
+    let included = dir.join("grid.toml");
+    fs::write(&included, "[grid]\nrows = 2\ncolumns = 3\n").unwrap();
+
+    let port = free_port();
+    let addr = format!("127.0.0.1:{}", port);
+    let _server = Reaper(server(&dir, port, &[]).spawn().expect("Failed to start the server"));
+    wait_for_listener(&addr);
+
+    let loaded = status(&addr, &Request::Status);
+    assert!(loaded.config_files.iter().any(|file| file.ends_with("grid.toml")));
+    assert!(!loaded.config_is_stale());
+
+    // A change within the same second as the load still counts.
+    let loaded_at = fs::metadata(&included).unwrap().modified().unwrap();
+    fs::write(&included, "[grid]\nrows = 4\ncolumns = 3\n").unwrap();
+    let later = loaded_at + Duration::from_millis(1);
+    fs::File::options().write(true).open(&included).unwrap().set_modified(later).unwrap();
+    assert!(status(&addr, &Request::Status).config_is_stale());
+    let reloaded = status(&addr, &Request::Reload);
+    assert_eq!(reloaded.token_count, 12);
+    assert!(!reloaded.config_is_stale());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn second_server_is_refused_unless_replacing() {
+    let dir = scratch_dir("instance");
+    fs::write(dir.join("config.toml"), "").unwrap();
+    let pid_file = dir.join("gtk-cursor-navigator-server.pid");
+
+    let port = free_port();
+    let addr = format!("127.0.0.1:{}", port);
+    let mut first = Reaper(server(&dir, port, &[]).spawn().expect("Failed to start the server"));
+    wait_for_listener(&addr);
+    assert_eq!(fs::read_to_string(&pid_file).unwrap().trim(), first.0.id().to_string());
+
+    let refused = server(&dir, free_port(), &[]).status().unwrap();
+    assert_eq!(refused.code(), Some(1));
+
+    // The replacement takes over the same port, which the first server must free first.
+    let mut second = Reaper(server(&dir, port, &["--replace"]).spawn().unwrap());
+    assert!(first.0.wait().unwrap().success(), "The replaced server didn't stop cleanly");
+    wait_for_listener(&addr);
+    assert_eq!(second.0.try_wait().unwrap(), None, "The replacement failed to start");
+    assert_eq!(fs::read_to_string(&pid_file).unwrap().trim(), second.0.id().to_string());
+    assert_eq!(status(&addr, &Request::Status).clients_served, 1);
+
+    send_request(&addr, &Request::Shutdown).unwrap();
+    assert!(second.0.wait().unwrap().success());
+    assert!(!pid_file.exists(), "The pid file was left behind");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn set_profile_switches_every_client() {
+    let dir = scratch_dir("set-profile");
+    fs::write(
+        dir.join("config.toml"),
+        "[grid]\nrows = 2\ncolumns = 2\n[profiles.docked]\ngrid = { rows = 5, columns = 5 }\n",
+    )
+    .unwrap();
+    let port = free_port();
+    let addr = format!("127.0.0.1:{}", port);
+    let _server = Reaper(server(&dir, port, &[]).spawn().expect("Failed to start the server"));
+    wait_for_listener(&addr);
+
+    let shared_data = |addr: &str| -> SharedData {
+        let request = Request::SharedData { profile: None, window: None };
+        serde_json::from_str(&send_request(addr, &request).unwrap()).unwrap()
+    };
+    assert_eq!(shared_data(&addr).tokens.len(), 4);
+
+    let docked = Request::SetProfile { profile: Some("docked".to_string()) };
+    assert_eq!(status(&addr, &docked).active_profile.as_deref(), Some("docked"));
+    let data = shared_data(&addr);
+    assert_eq!(data.tokens.len(), 25);
+    assert_eq!(data.profile.as_deref(), Some("docked"));
+
+    let unknown = status(&addr, &Request::SetProfile { profile: Some("laptop".to_string()) });
+    assert_eq!(unknown.active_profile.as_deref(), Some("docked"));
+    assert!(unknown.last_error.is_some());
+
+    assert_eq!(status(&addr, &Request::SetProfile { profile: None }).active_profile, None);
+    assert_eq!(shared_data(&addr).tokens.len(), 4);
+    let _ = fs::remove_dir_all(&dir);
+}
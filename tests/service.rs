@@ -0,0 +1,94 @@
+//! Checks the pieces of running the server as a systemd user service: readiness
+//! notifications, the generated units, and taking the listening socket from socket
+//! activation, using `systemd-socket-activate` when it is installed.
+
+mod common;
+
+use std::fs;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::{free_port, scratch_dir, Reaper};
+use gtk_cursor_navigator::daemon;
+use gtk_cursor_navigator::protocol::{Request, ServerStatus, send_request};
+
+#[test]
+fn notifications_reach_path_and_abstract_sockets() {
+    let path = std::env::temp_dir().join(format!("gcn-notify-{}.sock", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let receiver = UnixDatagram::bind(&path).unwrap();
+    daemon::notify_socket(path.to_str().unwrap(), "READY=1").unwrap();
+    let mut buffer = [0; 64];
+    let length = receiver.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..length], b"READY=1");
+    let _ = fs::remove_file(&path);
+
+    let name = format!("gcn-notify-{}", std::process::id());
+    let receiver = UnixDatagram::bind_addr(&SocketAddr::from_abstract_name(&name).unwrap()).unwrap();
+    daemon::notify_socket(&format!("@{}", name), "STOPPING=1").unwrap();
+    let length = receiver.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..length], b"STOPPING=1");
+}
+
+#[test]
+fn units_quote_arguments_and_pair_up() {
+    let socket = daemon::socket_unit("gcn", "127.0.0.1:7878");
+    assert!(socket.contains("\nListenStream=127.0.0.1:7878\n"));
+
+    let service = daemon::service_unit(
+        "gcn",
+        Path::new("/usr/bin/gcn"),
+        &["--config".to_string(), "/home/me/My Configs/100%.toml".to_string()],
+    );
+    assert!(service.contains("\nRequires=gcn.socket\n"));
+    assert!(service.contains("\nType=notify\n"));
+    assert!(service.contains("\nExecStart=/usr/bin/gcn --config \"/home/me/My Configs/100%%.toml\"\n"));
+}
+
+#[test]
+fn server_uses_the_activation_socket() {
+    let found = Command::new("systemd-socket-activate")
+        .arg("--help")
+        .stdout(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !found {
+        eprintln!("Skipping: systemd-socket-activate not found");
+        return;
+    }
+
+    let dir = scratch_dir("activation");
+    let config_file = dir.join("config.toml");
+    fs::write(&config_file, "[grid]\nrows = 3\ncolumns = 3\n").unwrap();
+    let port = free_port();
+    let addr = format!("127.0.0.1:{}", port);
+
+    // `--port` points elsewhere, so only the passed socket can answer.
+    let _activator = Reaper(
+        Command::new("systemd-socket-activate")
+            .args(["--listen", &addr, "--setenv", &format!("XDG_RUNTIME_DIR={}", dir.display())])
+            .arg(env!("CARGO_BIN_EXE_gtk-cursor-navigator-server"))
+            .args(["--config", config_file.to_str().unwrap(), "--port", "1"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap(),
+    );
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let status = loop {
+        if let Ok(answer) = send_request(&addr, &Request::Status)
+            && let Ok(status) = serde_json::from_str::<ServerStatus>(&answer)
+        {
+            break status;
+        }
+        assert!(Instant::now() < deadline, "No answer on the activation socket");
+        thread::sleep(Duration::from_millis(100));
+    };
+    assert_eq!(status.token_count, 9);
+
+    let _ = fs::remove_dir_all(&dir);
+}
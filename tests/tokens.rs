@@ -0,0 +1,13 @@
+//! Token generation from the configured keys.
+
+use gtk_cursor_navigator::conf::ConfGrid;
+use gtk_cursor_navigator::{generate_token_list, token_capacity, SelectedKeys};
+
+#[test]
+fn capacity_is_the_number_of_tokens_the_keys_make() {
+    let grid = ConfGrid::default();
+    let keys = SelectedKeys::new(&grid.key_left, &grid.key_right);
+    let capacity = token_capacity(&keys);
+    assert_eq!(generate_token_list(capacity, &keys).len(), capacity);
+    assert!(std::panic::catch_unwind(|| generate_token_list(capacity + 1, &keys)).is_err());
+}